@@ -0,0 +1,78 @@
+//! Heap allocations and time of a full grid update and draw.
+//!
+//! Run with `cargo bench --bench grid`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use mandelbrot::{MandelbrotGrid, Strategy};
+
+const WIDTH: usize = 1000;
+const HEIGHT: usize = 1000;
+const RUNS: usize = 5;
+
+/// System allocator that counts allocations.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Allocations, allocated bytes and fastest time of `RUNS` runs of `f`.
+fn measure(mut f: impl FnMut()) -> (usize, usize, Duration) {
+    let mut best = Duration::MAX;
+    let (mut allocations, mut bytes) = (0, 0);
+    for _ in 0..RUNS {
+        let before = (
+            ALLOCATIONS.load(Ordering::Relaxed),
+            BYTES.load(Ordering::Relaxed),
+        );
+        let start = Instant::now();
+        f();
+        best = best.min(start.elapsed());
+        allocations = ALLOCATIONS.load(Ordering::Relaxed) - before.0;
+        bytes = BYTES.load(Ordering::Relaxed) - before.1;
+    }
+    (allocations, bytes, best)
+}
+
+fn report(name: &str, (allocations, bytes, time): (usize, usize, Duration)) {
+    println!(
+        "{name:<8} {allocations:>9} allocations {:>8.1} MiB {time:>12.3?}",
+        bytes as f64 / (1024.0 * 1024.0)
+    );
+}
+
+fn main() {
+    println!("{WIDTH}x{HEIGHT} grid, default view");
+    report("new", measure(|| drop(MandelbrotGrid::new(WIDTH, HEIGHT))));
+
+    let mut grid = MandelbrotGrid::new(WIDTH, HEIGHT);
+    grid.strategy = Strategy::Full;
+    report(
+        "update",
+        measure(|| {
+            // Alternate the limit so no update can reuse the previous cells.
+            grid.max_iters ^= 1;
+            grid.update();
+        }),
+    );
+
+    let mut frame = vec![0; 4 * WIDTH * HEIGHT];
+    report("draw", measure(|| grid.draw(&mut frame)));
+}
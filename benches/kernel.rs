@@ -0,0 +1,72 @@
+//! Compares the scalar and SIMD escape-time kernels on one thread.
+//!
+//! Run with `cargo bench --bench kernel`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use mandelbrot::{escape_lanes, escape_with, simd_compiled, Complex, Mandelbrot, LANES};
+
+const WIDTH: usize = 400;
+const HEIGHT: usize = 300;
+const RUNS: usize = 5;
+
+/// Parameters of a `WIDTH`x`HEIGHT` grid over the rectangle from `min` to
+/// `max`.
+fn points(min: (f64, f64), max: (f64, f64)) -> Vec<Complex<f64>> {
+    let mut points = Vec::with_capacity(WIDTH * HEIGHT);
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let re = min.0 + (max.0 - min.0) * x as f64 / WIDTH as f64;
+            let im = min.1 + (max.1 - min.1) * y as f64 / HEIGHT as f64;
+            points.push(Complex::new(re, im));
+        }
+    }
+    points
+}
+
+/// Fastest of `RUNS` runs of `f`.
+fn time(mut f: impl FnMut()) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+/// Times both kernels on `points` and prints the results under `name`.
+fn compare(name: &str, points: &[Complex<f64>], max_iters: usize) {
+    let zero = Complex::new(0.0, 0.0);
+
+    let scalar = time(|| {
+        for &c in points {
+            black_box(escape_with(&Mandelbrot, zero, black_box(c), max_iters));
+        }
+    });
+    let simd = time(|| {
+        for c in points.chunks_exact(LANES) {
+            let c = std::array::from_fn(|lane| c[lane]);
+            black_box(escape_lanes([zero; LANES], black_box(c), max_iters));
+        }
+    });
+
+    let per_pixel = |d: Duration| d.as_nanos() as f64 / points.len() as f64;
+    println!("{name}: {WIDTH}x{HEIGHT} pixels, max iters {max_iters}");
+    println!("  scalar: {scalar:?} ({:.1} ns/pixel)", per_pixel(scalar));
+    println!(
+        "  simd:   {simd:?} ({:.1} ns/pixel, {:.2}x)",
+        per_pixel(simd),
+        scalar.as_secs_f64() / simd.as_secs_f64()
+    );
+}
+
+fn main() {
+    println!("SIMD compiled in: {}", simd_compiled());
+    compare("full set", &points((-2.5, -1.3125), (1.0, 1.3125)), 500);
+    // Almost all of it inside the main cardioid, where the orbits stop at
+    // a detected period rather than escaping.
+    compare("interior", &points((-0.6, -0.3), (0.2, 0.3)), 5000);
+}
@@ -0,0 +1,408 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+use num::bigint::BigInt;
+use num::complex::Complex;
+use num::traits::{Float, Signed, ToPrimitive, Zero};
+
+/// Fraction bits used when a value is created without an explicit precision.
+pub const DEFAULT_FRAC_BITS: u32 = 64;
+/// Most digits or decimal places of a parsed decimal, written out without
+/// an exponent. Views down to extents of 1e-300 need centers of about 300
+/// digits; longer ones would only cost time and memory.
+pub const MAX_DECIMAL_DIGITS: usize = 1000;
+
+/// Arbitrary-precision binary fixed-point number, `mantissa / 2^frac_bits`.
+///
+/// Operations on two values keep the larger of the two precisions, so a
+/// computation runs at the precision of its most precise input. Products are
+/// truncated towards negative infinity.
+#[derive(Clone, Debug)]
+pub struct BigFixed {
+    mantissa: BigInt,
+    frac_bits: u32,
+}
+
+impl BigFixed {
+    pub fn zero(frac_bits: u32) -> Self {
+        Self {
+            mantissa: BigInt::zero(),
+            frac_bits,
+        }
+    }
+
+    /// Converts `value` exactly, or truncated if it has more than
+    /// `frac_bits` fraction bits. Non-finite values become zero.
+    pub fn from_f64(value: f64, frac_bits: u32) -> Self {
+        if !value.is_finite() {
+            return Self::zero(frac_bits);
+        }
+        let (mantissa, exponent, sign) = value.integer_decode();
+        let mut mantissa = BigInt::from(mantissa);
+        let shift = exponent as i64 + frac_bits as i64;
+        if shift >= 0 {
+            mantissa <<= shift as usize;
+        } else {
+            mantissa >>= (-shift) as usize;
+        }
+        if sign < 0 {
+            mantissa = -mantissa;
+        }
+        Self {
+            mantissa,
+            frac_bits,
+        }
+    }
+
+    /// Nearest `f64`, keeping the full exponent range of `f64`.
+    pub fn to_f64(&self) -> f64 {
+        let bits = self.mantissa.bits();
+        // Drop everything below the 64 leading bits, which is more than an
+        // f64 can hold anyway, so the conversion below cannot overflow.
+        let shift = bits.saturating_sub(64);
+        let top = (&self.mantissa >> shift as usize).to_f64().unwrap_or(0.0);
+        let exponent = shift as i64 - self.frac_bits as i64;
+        // Scale in two halves so intermediate powers stay representable.
+        let half = (exponent / 2).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        let rest = (exponent - half as i64).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        top * 2f64.powi(half) * 2f64.powi(rest)
+    }
+
+    pub fn frac_bits(&self) -> u32 {
+        self.frac_bits
+    }
+
+    /// Returns the value rescaled to `frac_bits`, truncating if it shrinks.
+    pub fn with_frac_bits(&self, frac_bits: u32) -> Self {
+        let mantissa = match frac_bits.cmp(&self.frac_bits) {
+            Ordering::Greater => &self.mantissa << (frac_bits - self.frac_bits) as usize,
+            Ordering::Less => &self.mantissa >> (self.frac_bits - frac_bits) as usize,
+            Ordering::Equal => self.mantissa.clone(),
+        };
+        Self {
+            mantissa,
+            frac_bits,
+        }
+    }
+
+    pub fn abs(&self) -> Self {
+        Self {
+            mantissa: self.mantissa.abs(),
+            frac_bits: self.frac_bits,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.mantissa.is_negative()
+    }
+
+    /// Parses a decimal such as `-0.743643887037158704752191506114774` or
+    /// `1.5e-3`, rounding to `frac_bits` fraction bits.
+    pub fn parse(s: &str, frac_bits: u32) -> Option<Self> {
+        let (digits, scale) = parse_decimal(s)?;
+        let mut mantissa = digits << frac_bits as usize;
+        if scale >= 0 {
+            let divisor = BigInt::from(10).pow(scale as u32);
+            // Round half away from zero.
+            let half = &divisor / 2;
+            mantissa = if mantissa.is_negative() {
+                (mantissa - half) / divisor
+            } else {
+                (mantissa + half) / divisor
+            };
+        } else {
+            mantissa *= BigInt::from(10).pow((-scale) as u32);
+        }
+        Some(Self {
+            mantissa,
+            frac_bits,
+        })
+    }
+
+    /// Brings both operands to the larger precision.
+    fn aligned(&self, other: &Self) -> (BigInt, BigInt, u32) {
+        let frac_bits = self.frac_bits.max(other.frac_bits);
+        let a = self.with_frac_bits(frac_bits).mantissa;
+        let b = other.with_frac_bits(frac_bits).mantissa;
+        (a, b, frac_bits)
+    }
+}
+
+/// Splits a decimal into its digits as an integer and the power of ten they
+/// are divided by. Fails for more than [`MAX_DECIMAL_DIGITS`] digits or
+/// decimal places.
+fn parse_decimal(s: &str) -> Option<(BigInt, i64)> {
+    let s = s.trim();
+    let (s, exponent) = match s.find(['e', 'E']) {
+        Some(pos) => (&s[..pos], s[pos + 1..].parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    if int.is_empty() && frac.is_empty() {
+        return None;
+    }
+    if !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if int.len() + frac.len() > MAX_DECIMAL_DIGITS {
+        return None;
+    }
+    let digits: BigInt = format!("{int}{frac}").parse().ok()?;
+    let scale = (frac.len() as i64).checked_sub(exponent)?;
+    if scale.unsigned_abs() > MAX_DECIMAL_DIGITS as u64 {
+        return None;
+    }
+    Some((if negative { -digits } else { digits }, scale))
+}
+
+impl FromStr for BigFixed {
+    type Err = ();
+
+    /// Parses a decimal with enough precision for every digit it contains,
+    /// see [`MAX_DECIMAL_DIGITS`].
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (_, scale) = parse_decimal(s).ok_or(())?;
+        // log2(10) < 3.33 bits per decimal digit, plus some guard bits.
+        let frac_bits = (scale.max(0) as f64 * 3.33) as u32 + 16;
+        Self::parse(s, frac_bits.max(DEFAULT_FRAC_BITS)).ok_or(())
+    }
+}
+
+impl fmt::Display for BigFixed {
+    /// Formats as a decimal with as many digits as the precision resolves,
+    /// or with the requested precision.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = f
+            .precision()
+            .unwrap_or((self.frac_bits as f64 * std::f64::consts::LOG10_2) as usize);
+        // Round to the last printed digit.
+        let half = BigInt::from(1) << self.frac_bits as usize >> 1usize;
+        let scaled = (self.mantissa.abs() * BigInt::from(10).pow(digits as u32) + half)
+            >> self.frac_bits as usize;
+        let text = format!("{:0>width$}", scaled.to_string(), width = digits + 1);
+        let (int, frac) = text.split_at(text.len() - digits);
+        let frac = if f.precision().is_some() {
+            frac
+        } else {
+            frac.trim_end_matches('0')
+        };
+        let sign = if self.is_negative() { "-" } else { "" };
+        if frac.is_empty() {
+            write!(f, "{sign}{int}")
+        } else {
+            write!(f, "{sign}{int}.{frac}")
+        }
+    }
+}
+
+impl PartialEq for BigFixed {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b, _) = self.aligned(other);
+        a == b
+    }
+}
+
+impl Add for &BigFixed {
+    type Output = BigFixed;
+
+    fn add(self, other: &BigFixed) -> BigFixed {
+        let (a, b, frac_bits) = self.aligned(other);
+        BigFixed {
+            mantissa: a + b,
+            frac_bits,
+        }
+    }
+}
+
+impl Sub for &BigFixed {
+    type Output = BigFixed;
+
+    fn sub(self, other: &BigFixed) -> BigFixed {
+        let (a, b, frac_bits) = self.aligned(other);
+        BigFixed {
+            mantissa: a - b,
+            frac_bits,
+        }
+    }
+}
+
+impl Mul for &BigFixed {
+    type Output = BigFixed;
+
+    fn mul(self, other: &BigFixed) -> BigFixed {
+        let frac_bits = self.frac_bits.max(other.frac_bits);
+        let drop = self.frac_bits + other.frac_bits - frac_bits;
+        BigFixed {
+            mantissa: (&self.mantissa * &other.mantissa) >> drop as usize,
+            frac_bits,
+        }
+    }
+}
+
+impl Neg for &BigFixed {
+    type Output = BigFixed;
+
+    fn neg(self) -> BigFixed {
+        BigFixed {
+            mantissa: -&self.mantissa,
+            frac_bits: self.frac_bits,
+        }
+    }
+}
+
+/// Complex number with [`BigFixed`] parts, for high precision iteration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BigComplex {
+    pub re: BigFixed,
+    pub im: BigFixed,
+}
+
+impl BigComplex {
+    pub fn new(re: BigFixed, im: BigFixed) -> Self {
+        Self { re, im }
+    }
+
+    pub fn from_f64(z: Complex<f64>, frac_bits: u32) -> Self {
+        Self::new(
+            BigFixed::from_f64(z.re, frac_bits),
+            BigFixed::from_f64(z.im, frac_bits),
+        )
+    }
+
+    pub fn to_f64(&self) -> Complex<f64> {
+        Complex::new(self.re.to_f64(), self.im.to_f64())
+    }
+
+    pub fn square(&self) -> Self {
+        let re = &(&self.re + &self.im) * &(&self.re - &self.im);
+        let im = &self.re * &self.im;
+        let im = &im + &im;
+        Self::new(re, im)
+    }
+
+    pub fn conj(&self) -> Self {
+        Self::new(self.re.clone(), -&self.im)
+    }
+}
+
+impl Add for &BigComplex {
+    type Output = BigComplex;
+
+    fn add(self, other: &BigComplex) -> BigComplex {
+        BigComplex::new(&self.re + &other.re, &self.im + &other.im)
+    }
+}
+
+impl Mul for &BigComplex {
+    type Output = BigComplex;
+
+    fn mul(self, other: &BigComplex) -> BigComplex {
+        let re = &(&self.re * &other.re) - &(&self.im * &other.im);
+        let im = &(&self.re * &other.im) + &(&self.im * &other.re);
+        BigComplex::new(re, im)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigFixed {
+        s.parse().expect(s)
+    }
+
+    #[test]
+    fn parse_display_round_trip() {
+        for s in [
+            "0",
+            "1.5",
+            "-2.25",
+            "-0.743643887037158704752191506114774",
+            "0.131825904205311970493132056385139",
+            "123456789.000000000000000000001",
+        ] {
+            assert_eq!(big(s).to_string(), s);
+        }
+        assert_eq!(big("1.5e-3").to_string(), "0.0015");
+        assert_eq!(big("-25e2").to_string(), "-2500");
+        assert!("".parse::<BigFixed>().is_err());
+        assert!("1.2.3".parse::<BigFixed>().is_err());
+        assert!("0x10".parse::<BigFixed>().is_err());
+    }
+
+    #[test]
+    fn parse_rejects_excessive_precision() {
+        // More fraction bits than fit in a `u32`.
+        assert!("1e-2000000000".parse::<BigFixed>().is_err());
+        assert!("1e-99999999999".parse::<BigFixed>().is_err());
+        // Would take minutes and gigabytes to parse and iterate with.
+        assert!("1e-999999999".parse::<BigFixed>().is_err());
+        assert!("1e999999999".parse::<BigFixed>().is_err());
+        assert!(BigFixed::parse("1e-999999999", DEFAULT_FRAC_BITS).is_none());
+        let long = format!("0.{}", "1".repeat(MAX_DECIMAL_DIGITS));
+        assert!(long.parse::<BigFixed>().is_err());
+        // Deep zoom centers are well within the limits.
+        let deep = format!("-0.{}", "7".repeat(400));
+        assert!(deep.parse::<BigFixed>().is_ok());
+        assert!(format!("1e-{MAX_DECIMAL_DIGITS}")
+            .parse::<BigFixed>()
+            .is_ok());
+    }
+
+    #[test]
+    fn mul_truncates_towards_negative_infinity() {
+        let sixteenth = BigFixed::parse("0.0625", 4).unwrap();
+        assert_eq!(&sixteenth * &sixteenth, BigFixed::zero(4));
+        assert_eq!((&-&sixteenth * &sixteenth).to_f64(), -0.0625);
+        // Exact products stay exact.
+        let (a, b) = (big("1.5"), big("-2.25"));
+        assert_eq!((&a * &b).to_f64(), -3.375);
+        // The product keeps the larger precision.
+        let coarse = BigFixed::from_f64(3.0, 2);
+        assert_eq!((&coarse * &big("0.1")).frac_bits(), DEFAULT_FRAC_BITS);
+    }
+
+    #[test]
+    fn f64_conversions() {
+        for value in [0.0, 1.0, -1.0, 0.1, -2.5e-10, 1e30, std::f64::consts::PI] {
+            assert_eq!(BigFixed::from_f64(value, 1100).to_f64(), value);
+        }
+        // Tiny values keep their exponent.
+        assert_eq!(BigFixed::from_f64(1e-300, 1100).to_f64(), 1e-300);
+        // Bits below the precision are truncated.
+        assert_eq!(BigFixed::from_f64(0.75, 1).to_f64(), 0.5);
+        assert_eq!(BigFixed::from_f64(-0.75, 1).to_f64(), -0.5);
+        assert_eq!(BigFixed::from_f64(f64::NAN, 8), BigFixed::zero(8));
+    }
+
+    #[test]
+    fn negative_values() {
+        let a = big("-0.5");
+        assert!(a.is_negative());
+        assert!(!a.abs().is_negative());
+        assert_eq!((&a + &big("0.25")).to_string(), "-0.25");
+        assert_eq!((&a - &big("0.25")).to_string(), "-0.75");
+        assert_eq!((&a * &a).to_string(), "0.25");
+        assert_eq!((-&a).to_string(), "0.5");
+    }
+
+    #[test]
+    fn carries() {
+        let quarter = BigFixed::parse("0.25", 2).unwrap();
+        let three_quarters = BigFixed::parse("0.75", 2).unwrap();
+        assert_eq!((&quarter + &three_quarters).to_string(), "1");
+        // Across the 64 bit limbs of the mantissa.
+        let below = BigFixed::from_f64(1.0, 200);
+        let tiny = BigFixed::parse("1e-60", 200).unwrap();
+        assert!(&(&below + &tiny) - &below == tiny);
+        // Rounding the last printed digit carries into the integer part.
+        assert_eq!(format!("{:.1}", big("0.96")), "1.0");
+        assert_eq!(format!("{:.2}", big("-9.999")), "-10.00");
+    }
+}
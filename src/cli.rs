@@ -0,0 +1,579 @@
+use std::fmt;
+use std::fs::File;
+use std::io::BufWriter;
+use std::process::ExitCode;
+
+use mandelbrot::{
+    BigFixed, ColorMode, Complex, Fractal, InteriorMode, Interpolation, Lighting, MandelbrotGrid,
+    OrbitTrap, Palette, PaletteError, Pattern, Repeat, Shading, Strategy, Supersampling, Viewport,
+    MAX_DECIMAL_DIGITS, MAX_ITERS,
+};
+
+/// Width of the complex plane shown by the default viewport.
+const DEFAULT_EXTENT: f64 = 5.0;
+
+#[derive(Debug)]
+pub enum CliError {
+    /// Bad or missing command line arguments.
+    Usage(String),
+    /// Failure while creating or writing the output file.
+    Io(String, std::io::Error),
+    /// Failure while encoding the image.
+    Png(String, png::EncodingError),
+    /// Failure while loading a palette file.
+    Palette(String, PaletteError),
+}
+
+impl CliError {
+    pub fn is_usage(&self) -> bool {
+        matches!(self, CliError::Usage(_))
+    }
+
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            CliError::Usage(_) => ExitCode::from(2),
+            CliError::Io(..) | CliError::Png(..) | CliError::Palette(..) => ExitCode::FAILURE,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) => write!(f, "{msg}"),
+            CliError::Io(path, err) => write!(f, "{path}: {err}"),
+            CliError::Png(path, err) => write!(f, "{path}: {err}"),
+            CliError::Palette(path, err) => write!(f, "{path}: {err}"),
+        }
+    }
+}
+
+fn usage(msg: impl Into<String>) -> CliError {
+    CliError::Usage(msg.into())
+}
+
+/// Options of the `view` subcommand.
+#[derive(Debug)]
+pub struct ViewArgs {
+    /// Physical pixels of the window per grid pixel along each axis.
+    pub render_scale: usize,
+}
+
+impl Default for ViewArgs {
+    fn default() -> Self {
+        Self { render_scale: 1 }
+    }
+}
+
+impl ViewArgs {
+    pub fn parse(args: &[String]) -> Result<Self, CliError> {
+        let mut view = Self::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| iter.next().cloned())
+                    .ok_or_else(|| usage(format!("missing value for `{flag}`")))
+            };
+            match flag {
+                "--render-scale" => view.render_scale = parse_count(flag, &value()?)?,
+                _ => return Err(usage(format!("unknown argument `{arg}`"))),
+            }
+        }
+        Ok(view)
+    }
+}
+
+#[derive(Debug)]
+struct RenderArgs {
+    center: (BigFixed, BigFixed),
+    julia: Option<(f64, f64)>,
+    formula: Fractal,
+    extent: f64,
+    width: usize,
+    height: usize,
+    max_iters: usize,
+    auto_iters: bool,
+    color_mode: ColorMode,
+    palette: Palette,
+    lighting: Option<Lighting>,
+    trap: OrbitTrap,
+    interior: InteriorMode,
+    interior_palette: Palette,
+    supersampling: Option<Supersampling>,
+    strategy: Strategy,
+    output: String,
+}
+
+impl RenderArgs {
+    fn parse(args: &[String]) -> Result<Self, CliError> {
+        let mut center = (BigFixed::from_f64(0.0, 0), BigFixed::from_f64(0.0, 0));
+        let mut julia = None;
+        let mut formula = Fractal::default();
+        let mut extent = None;
+        let mut zoom = None;
+        let mut width = 1000;
+        let mut height = 1000;
+        let mut max_iters = MAX_ITERS;
+        let mut auto_iters = false;
+        let mut color_mode = ColorMode::default();
+        let mut palette = Palette::default();
+        let mut line_width = None;
+        let mut trap = None;
+        let mut stripe_density = None;
+        let mut skip = None;
+        let mut palette_offset = None;
+        let mut palette_scale = None;
+        let mut palette_repeat = None;
+        let mut interpolation = None;
+        let mut lighting = None;
+        let mut light_angle = None;
+        let mut light_height = None;
+        let mut light_intensity = None;
+        let mut interior = InteriorMode::default();
+        let mut interior_palette = None;
+        let mut supersample = None;
+        let mut sample_pattern = None;
+        let mut adaptive_threshold = None;
+        let mut strategy = Strategy::default();
+        let mut output = None;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            // Accept both `--flag value` and `--flag=value`.
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| iter.next().cloned())
+                    .ok_or_else(|| usage(format!("missing value for `{flag}`")))
+            };
+            match flag {
+                "--center" => center = parse_big_complex(flag, &value()?)?,
+                "--formula" => formula = parse_formula(&value()?)?,
+                "--julia" => julia = Some(parse_complex(flag, &value()?)?),
+                "--extent" => extent = Some(parse_positive(flag, &value()?)?),
+                "--zoom" => zoom = Some(parse_positive(flag, &value()?)?),
+                "--width" => width = parse_count(flag, &value()?)?,
+                "--height" => height = parse_count(flag, &value()?)?,
+                "--max-iters" => max_iters = parse_count(flag, &value()?)?,
+                "--auto-iters" if inline.is_none() => auto_iters = true,
+                "--coloring" => color_mode = parse_color_mode(&value()?)?,
+                "--line-width" => line_width = Some(parse_positive(flag, &value()?)?),
+                "--trap" => trap = Some(parse_trap(&value()?)?),
+                "--stripe-density" => stripe_density = Some(parse_positive(flag, &value()?)?),
+                "--skip" => skip = Some(parse_index(flag, &value()?)?),
+                "--palette" => palette = parse_palette(flag, &value()?)?,
+                "--palette-offset" => palette_offset = Some(parse_finite(flag, &value()?)?),
+                "--palette-scale" => palette_scale = Some(parse_positive(flag, &value()?)?),
+                "--palette-repeat" => palette_repeat = Some(parse_repeat(&value()?)?),
+                "--interpolation" => interpolation = Some(parse_interpolation(&value()?)?),
+                "--interior" => interior = parse_interior(&value()?)?,
+                "--interior-palette" => interior_palette = Some(parse_palette(flag, &value()?)?),
+                "--supersample" => supersample = Some(parse_count(flag, &value()?)?),
+                "--sample-pattern" => sample_pattern = Some(parse_pattern(&value()?)?),
+                "--adaptive-threshold" => {
+                    adaptive_threshold = Some(parse_fraction(flag, &value()?)?)
+                }
+                "--lighting" => lighting = parse_lighting(&value()?)?,
+                "--light-angle" => light_angle = Some(parse_finite(flag, &value()?)?),
+                "--light-height" => light_height = Some(parse_positive(flag, &value()?)?),
+                "--light-intensity" => light_intensity = Some(parse_fraction(flag, &value()?)?),
+                "--strategy" => strategy = parse_strategy(&value()?)?,
+                "-o" | "--output" => output = Some(value()?),
+                _ => return Err(usage(format!("unknown argument `{arg}`"))),
+            }
+        }
+
+        let extent = match (extent, zoom) {
+            (Some(_), Some(_)) => return Err(usage("`--extent` and `--zoom` are exclusive")),
+            (Some(extent), None) => extent,
+            (None, Some(zoom)) => DEFAULT_EXTENT / zoom,
+            (None, None) => DEFAULT_EXTENT,
+        };
+        let output = output.ok_or_else(|| usage("missing `--output`"))?;
+        if let Some(width) = line_width {
+            match &mut color_mode {
+                ColorMode::Distance { line_width } => *line_width = width,
+                _ => return Err(usage("`--line-width` needs `--coloring distance`")),
+            }
+        }
+        if trap.is_some() && !color_mode.uses_trap() {
+            return Err(usage("`--trap` needs `--coloring trap`"));
+        }
+        if let Some(value) = stripe_density {
+            match &mut color_mode {
+                ColorMode::Stripe { density, .. } => *density = value,
+                _ => return Err(usage("`--stripe-density` needs `--coloring stripe`")),
+            }
+        }
+        if let Some(value) = skip {
+            match &mut color_mode {
+                ColorMode::Stripe { skip, .. }
+                | ColorMode::Curvature { skip }
+                | ColorMode::TriangleInequality { skip } => *skip = value,
+                _ => {
+                    return Err(usage(
+                        "`--skip` needs `--coloring stripe`, `curvature` or `triangle-inequality`",
+                    ))
+                }
+            }
+        }
+        palette.offset = palette_offset.unwrap_or(palette.offset);
+        palette.scale = palette_scale.unwrap_or(palette.scale);
+        palette.repeat = palette_repeat.unwrap_or(palette.repeat);
+        palette.interpolation = interpolation.unwrap_or(palette.interpolation);
+        if let Some(lighting) = &mut lighting {
+            lighting.angle = light_angle.unwrap_or(lighting.angle);
+            lighting.height = light_height.unwrap_or(lighting.height);
+            lighting.intensity = light_intensity.unwrap_or(lighting.intensity);
+        } else if light_angle.is_some() || light_height.is_some() || light_intensity.is_some() {
+            return Err(usage("light options need `--lighting`"));
+        }
+        let supersampling = supersample.map(|factor| Supersampling {
+            pattern: sample_pattern.unwrap_or_default(),
+            factor,
+            threshold: adaptive_threshold,
+        });
+        if supersampling.is_none() && (sample_pattern.is_some() || adaptive_threshold.is_some()) {
+            return Err(usage("sampling options need `--supersample`"));
+        }
+        if width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(4))
+            .is_none()
+        {
+            return Err(usage(format!("image size {width}x{height} is too large")));
+        }
+        if u32::try_from(width).is_err() || u32::try_from(height).is_err() {
+            return Err(usage(format!(
+                "image size {width}x{height} is too large for PNG"
+            )));
+        }
+
+        Ok(Self {
+            center,
+            julia,
+            formula,
+            extent,
+            width,
+            height,
+            max_iters,
+            auto_iters,
+            color_mode,
+            palette,
+            lighting,
+            trap: trap.unwrap_or_default(),
+            interior,
+            interior_palette: interior_palette.unwrap_or_default(),
+            supersampling,
+            strategy,
+            output,
+        })
+    }
+
+    /// Viewport spanning `extent` horizontally with square pixels.
+    fn viewport(&self) -> Viewport {
+        let scale = self.extent / self.width as f64;
+        let (re, im) = self.center.clone();
+        Viewport::with_scale(re, im, scale, self.width, self.height)
+    }
+}
+
+/// Parses `re,im` keeping every digit, for deep zoom centers.
+fn parse_big_complex(flag: &str, value: &str) -> Result<(BigFixed, BigFixed), CliError> {
+    let err = || {
+        usage(format!(
+            "invalid `{flag}` value `{value}`, expected `re,im` with at most \
+             {MAX_DECIMAL_DIGITS} digits or decimal places each"
+        ))
+    };
+    let (re, im) = value.split_once(',').ok_or_else(err)?;
+    let re: BigFixed = re.parse().map_err(|_| err())?;
+    let im: BigFixed = im.parse().map_err(|_| err())?;
+    Ok((re, im))
+}
+
+fn parse_complex(flag: &str, value: &str) -> Result<(f64, f64), CliError> {
+    let err = || {
+        usage(format!(
+            "invalid `{flag}` value `{value}`, expected `re,im`"
+        ))
+    };
+    let (re, im) = value.split_once(',').ok_or_else(err)?;
+    let re: f64 = re.trim().parse().map_err(|_| err())?;
+    let im: f64 = im.trim().parse().map_err(|_| err())?;
+    if !re.is_finite() || !im.is_finite() {
+        return Err(err());
+    }
+    Ok((re, im))
+}
+
+fn parse_formula(value: &str) -> Result<Fractal, CliError> {
+    let err = || {
+        usage(format!(
+            "invalid `--formula` value `{value}`, expected `mandelbrot`, `burning-ship`, \
+             `tricorn`, `celtic` or `multibrot:<power>`"
+        ))
+    };
+    match value {
+        "mandelbrot" => Ok(Fractal::Mandelbrot),
+        "burning-ship" => Ok(Fractal::BurningShip),
+        "tricorn" => Ok(Fractal::Tricorn),
+        "celtic" => Ok(Fractal::Celtic),
+        _ => {
+            let power = value.strip_prefix("multibrot:").ok_or_else(err)?;
+            match power.parse::<f64>() {
+                Ok(power) if power.is_finite() && power > 1.0 => Ok(Fractal::Multibrot(power)),
+                _ => Err(err()),
+            }
+        }
+    }
+}
+
+fn parse_color_mode(value: &str) -> Result<ColorMode, CliError> {
+    match value {
+        "banded" => Ok(ColorMode::Banded),
+        "smooth" => Ok(ColorMode::Smooth),
+        "histogram" => Ok(ColorMode::Histogram),
+        "distance" => Ok(ColorMode::Distance {
+            line_width: ColorMode::DEFAULT_LINE_WIDTH,
+        }),
+        "trap" => Ok(ColorMode::Trap),
+        "stripe" => Ok(ColorMode::Stripe {
+            density: ColorMode::DEFAULT_STRIPE_DENSITY,
+            skip: ColorMode::DEFAULT_SKIP,
+        }),
+        "curvature" => Ok(ColorMode::Curvature {
+            skip: ColorMode::DEFAULT_SKIP,
+        }),
+        "triangle-inequality" => Ok(ColorMode::TriangleInequality {
+            skip: ColorMode::DEFAULT_SKIP,
+        }),
+        _ => Err(usage(format!(
+            "invalid `--coloring` value `{value}`, expected `banded`, `smooth`, `histogram`, \
+             `distance`, `trap`, `stripe`, `curvature` or `triangle-inequality`"
+        ))),
+    }
+}
+
+/// Parses `<shape>:re,im[,param]`, where the parameter is the angle of a
+/// line, the radius of a circle or the width of stalks.
+fn parse_trap(value: &str) -> Result<OrbitTrap, CliError> {
+    let err = || {
+        usage(format!(
+            "invalid `--trap` value `{value}`, expected `point:re,im`, `line:re,im,angle`, \
+             `cross:re,im`, `circle:re,im,radius` or `stalks:re,im,width`"
+        ))
+    };
+    let (shape, numbers) = value.split_once(':').ok_or_else(err)?;
+    let numbers = numbers
+        .split(',')
+        .map(|n| n.trim().parse::<f64>().ok().filter(|n| n.is_finite()))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(err)?;
+    let trap = match (shape, &numbers[..]) {
+        ("point", &[re, im]) => OrbitTrap::Point(Complex::new(re, im)),
+        ("line", &[re, im, angle]) => OrbitTrap::Line {
+            point: Complex::new(re, im),
+            angle,
+        },
+        ("cross", &[re, im]) => OrbitTrap::Cross(Complex::new(re, im)),
+        ("circle", &[re, im, radius]) if radius > 0.0 => OrbitTrap::Circle {
+            center: Complex::new(re, im),
+            radius,
+        },
+        ("stalks", &[re, im, width]) if width > 0.0 => OrbitTrap::Stalks {
+            center: Complex::new(re, im),
+            width,
+        },
+        _ => return Err(err()),
+    };
+    Ok(trap)
+}
+
+/// Parses a built-in palette name or loads a palette file.
+fn parse_palette(flag: &str, value: &str) -> Result<Palette, CliError> {
+    if let Some(palette) = Palette::builtin(value) {
+        return Ok(palette);
+    }
+    if !value.contains('.') {
+        let names: Vec<_> = Palette::builtin_names().collect();
+        return Err(usage(format!(
+            "invalid `{flag}` value `{value}`, expected one of {} or a palette file",
+            names.join(", ")
+        )));
+    }
+    Palette::load(value).map_err(|err| CliError::Palette(value.to_string(), err))
+}
+
+fn parse_interior(value: &str) -> Result<InteriorMode, CliError> {
+    match value {
+        "black" => Ok(InteriorMode::Black),
+        "magnitude" => Ok(InteriorMode::Magnitude),
+        "period" => Ok(InteriorMode::Period),
+        "distance" => Ok(InteriorMode::Distance),
+        "multiplier" => Ok(InteriorMode::Multiplier),
+        _ => Err(usage(format!(
+            "invalid `--interior` value `{value}`, expected `black`, `magnitude`, `period`, \
+             `distance` or `multiplier`"
+        ))),
+    }
+}
+
+fn parse_pattern(value: &str) -> Result<Pattern, CliError> {
+    match value {
+        "grid" => Ok(Pattern::Grid),
+        "jittered" => Ok(Pattern::Jittered),
+        _ => Err(usage(format!(
+            "invalid `--sample-pattern` value `{value}`, expected `grid` or `jittered`"
+        ))),
+    }
+}
+
+fn parse_repeat(value: &str) -> Result<Repeat, CliError> {
+    match value {
+        "wrap" => Ok(Repeat::Wrap),
+        "mirror" => Ok(Repeat::Mirror),
+        "clamp" => Ok(Repeat::Clamp),
+        _ => Err(usage(format!(
+            "invalid `--palette-repeat` value `{value}`, expected `wrap`, `mirror` or `clamp`"
+        ))),
+    }
+}
+
+fn parse_interpolation(value: &str) -> Result<Interpolation, CliError> {
+    match value {
+        "rgb" => Ok(Interpolation::Rgb),
+        "linear-rgb" => Ok(Interpolation::LinearRgb),
+        "oklab" => Ok(Interpolation::Oklab),
+        _ => Err(usage(format!(
+            "invalid `--interpolation` value `{value}`, expected `rgb`, `linear-rgb` or `oklab`"
+        ))),
+    }
+}
+
+fn parse_lighting(value: &str) -> Result<Option<Lighting>, CliError> {
+    let shading = match value {
+        "off" => return Ok(None),
+        "lambert" => Shading::Lambert,
+        "blinn-phong" => Shading::BlinnPhong,
+        _ => {
+            return Err(usage(format!(
+                "invalid `--lighting` value `{value}`, expected `off`, `lambert` or `blinn-phong`"
+            )))
+        }
+    };
+    Ok(Some(Lighting {
+        shading,
+        ..Lighting::default()
+    }))
+}
+
+fn parse_strategy(value: &str) -> Result<Strategy, CliError> {
+    match value {
+        "subdivide" => Ok(Strategy::Subdivide),
+        "full" => Ok(Strategy::Full),
+        _ => Err(usage(format!(
+            "invalid `--strategy` value `{value}`, expected `subdivide` or `full`"
+        ))),
+    }
+}
+
+fn parse_positive(flag: &str, value: &str) -> Result<f64, CliError> {
+    match value.parse::<f64>() {
+        Ok(v) if v.is_finite() && v > 0.0 => Ok(v),
+        _ => Err(usage(format!(
+            "invalid `{flag}` value `{value}`, expected a positive number"
+        ))),
+    }
+}
+
+fn parse_finite(flag: &str, value: &str) -> Result<f64, CliError> {
+    match value.parse::<f64>() {
+        Ok(v) if v.is_finite() => Ok(v),
+        _ => Err(usage(format!(
+            "invalid `{flag}` value `{value}`, expected a number"
+        ))),
+    }
+}
+
+fn parse_fraction(flag: &str, value: &str) -> Result<f64, CliError> {
+    match value.parse::<f64>() {
+        Ok(v) if (0.0..=1.0).contains(&v) => Ok(v),
+        _ => Err(usage(format!(
+            "invalid `{flag}` value `{value}`, expected a number from 0 to 1"
+        ))),
+    }
+}
+
+fn parse_index(flag: &str, value: &str) -> Result<usize, CliError> {
+    value.parse::<usize>().map_err(|_| {
+        usage(format!(
+            "invalid `{flag}` value `{value}`, expected a non-negative integer"
+        ))
+    })
+}
+
+fn parse_count(flag: &str, value: &str) -> Result<usize, CliError> {
+    match value.parse::<usize>() {
+        Ok(v) if v > 0 => Ok(v),
+        _ => Err(usage(format!(
+            "invalid `{flag}` value `{value}`, expected a positive integer"
+        ))),
+    }
+}
+
+/// Entry point of the `render` subcommand.
+pub fn run(args: &[String]) -> Result<(), CliError> {
+    let args = RenderArgs::parse(args)?;
+    // Open the output first so a bad path fails before the render.
+    let file = File::create(&args.output).map_err(|err| CliError::Io(args.output.clone(), err))?;
+
+    let mut grid = MandelbrotGrid::new(args.width, args.height);
+    grid.viewport = args.viewport();
+    grid.max_iters = args.max_iters;
+    grid.auto_iters = args.auto_iters;
+    grid.color_mode = args.color_mode;
+    grid.palette = args.palette;
+    grid.lighting = args.lighting;
+    grid.trap = args.trap;
+    grid.interior = args.interior;
+    grid.interior_palette = args.interior_palette;
+    grid.supersampling = args.supersampling;
+    grid.julia = args.julia;
+    grid.formula = args.formula;
+    grid.strategy = args.strategy;
+    let stats = grid.update();
+    println!("{stats}");
+
+    let mut buffer = vec![0; 4 * args.width * args.height];
+    grid.draw(&mut buffer);
+    write_png(
+        file,
+        &args.output,
+        args.width as u32,
+        args.height as u32,
+        &buffer,
+    )?;
+    println!("Wrote {}", args.output);
+    Ok(())
+}
+
+fn write_png(file: File, path: &str, width: u32, height: u32, rgba: &[u8]) -> Result<(), CliError> {
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let png_err = |err| CliError::Png(path.to_string(), err);
+    let mut writer = encoder.write_header().map_err(png_err)?;
+    writer.write_image_data(rgba).map_err(png_err)?;
+    writer.finish().map_err(png_err)
+}
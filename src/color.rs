@@ -0,0 +1,337 @@
+use std::f64::consts::TAU;
+
+use crate::kernel::{Average, Escape};
+use crate::palette::Palette;
+
+/// How escape counts are turned into colors.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorMode {
+    /// Integer escape counts; shows the classic iteration bands.
+    Banded,
+    /// Continuous escape counts; gradients without banding.
+    #[default]
+    Smooth,
+    /// Continuous escape counts spread evenly over the palette by their
+    /// distribution across the grid, so views whose pixels escape within a
+    /// narrow range still use every color.
+    Histogram,
+    /// Continuous escape counts darkened towards the boundary of the set by
+    /// its estimated distance, which draws crisp boundary lines and brings
+    /// out filaments thinner than a pixel.
+    Distance {
+        /// Width of the boundary lines in pixels.
+        line_width: f64,
+    },
+    /// Closest approach of every orbit to an orbit trap, inside the set as
+    /// well. Orbits that missed the trap get smooth coloring.
+    Trap,
+    /// Stripes radiating from the set, see [`Average::Stripe`].
+    Stripe { density: f64, skip: usize },
+    /// Texture following how the orbits bend, see [`Average::Curvature`].
+    Curvature { skip: usize },
+    /// Texture from triangle inequality averaging, see
+    /// [`Average::TriangleInequality`].
+    TriangleInequality { skip: usize },
+}
+
+impl ColorMode {
+    pub const DEFAULT_LINE_WIDTH: f64 = 1.0;
+    pub const DEFAULT_STRIPE_DENSITY: f64 = 5.0;
+    /// Iterates left out of the orbit averages by default.
+    pub const DEFAULT_SKIP: usize = 1;
+
+    pub fn next(self) -> Self {
+        match self {
+            ColorMode::Banded => ColorMode::Smooth,
+            ColorMode::Smooth => ColorMode::Histogram,
+            ColorMode::Histogram => ColorMode::Distance {
+                line_width: Self::DEFAULT_LINE_WIDTH,
+            },
+            ColorMode::Distance { .. } => ColorMode::Trap,
+            ColorMode::Trap => ColorMode::Stripe {
+                density: Self::DEFAULT_STRIPE_DENSITY,
+                skip: Self::DEFAULT_SKIP,
+            },
+            ColorMode::Stripe { .. } => ColorMode::Curvature {
+                skip: Self::DEFAULT_SKIP,
+            },
+            ColorMode::Curvature { .. } => ColorMode::TriangleInequality {
+                skip: Self::DEFAULT_SKIP,
+            },
+            ColorMode::TriangleInequality { .. } => ColorMode::Banded,
+        }
+    }
+
+    /// Whether the mode needs the distance estimate of every pixel.
+    pub fn uses_distance(&self) -> bool {
+        matches!(self, ColorMode::Distance { .. })
+    }
+
+    /// Whether the mode needs the closest approach of every orbit to a trap.
+    pub fn uses_trap(&self) -> bool {
+        matches!(self, ColorMode::Trap)
+    }
+
+    /// Orbit average the mode colors by, if any.
+    pub fn average(&self) -> Option<Average> {
+        match *self {
+            ColorMode::Stripe { density, skip } => Some(Average::Stripe { density, skip }),
+            ColorMode::Curvature { skip } => Some(Average::Curvature { skip }),
+            ColorMode::TriangleInequality { skip } => Some(Average::TriangleInequality { skip }),
+            _ => None,
+        }
+    }
+}
+
+/// Colors `escape` according to `mode` with `palette`, for pixels
+/// `pixel_size` apart in the plane.
+///
+/// A single escape carries no distribution, so [`ColorMode::Histogram`] is
+/// colored like [`ColorMode::Smooth`] here; the grid equalizes over all its
+/// cells instead.
+pub fn escape_to_rgb(
+    escape: Escape,
+    max_iters: usize,
+    mode: ColorMode,
+    palette: &Palette,
+    pixel_size: f64,
+) -> [u8; 4] {
+    if let (ColorMode::Trap, Some(distance)) = (mode, escape.trap) {
+        return palette.trapped(distance);
+    }
+    if let (Some(_), Some(average)) = (mode.average(), escape.average) {
+        return palette.averaged(average);
+    }
+    let steps = match mode {
+        ColorMode::Banded => escape.steps as f64,
+        _ => escape.smooth,
+    };
+    let rgba = palette.color(steps / max_iters as f64);
+    match (mode, escape.distance) {
+        (ColorMode::Distance { line_width }, Some(distance)) => {
+            // Black within the line, with a one pixel wide ramp so the edge
+            // is antialiased.
+            let shade = (distance / pixel_size - line_width + 1.0).clamp(0.0, 1.0);
+            let [r, g, b, a] = rgba;
+            let dim = |c: u8| (c as f64 * shade).round() as u8;
+            [dim(r), dim(g), dim(b), a]
+        }
+        _ => rgba,
+    }
+}
+
+/// How points that never escape are colored, independently of the
+/// [`ColorMode`] and palette of the exterior.
+///
+/// The modes other than [`InteriorMode::Magnitude`] need the attracting
+/// cycle, see [`crate::Statistics::cycle`]; interior points without one stay
+/// black.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InteriorMode {
+    #[default]
+    Black,
+    /// Magnitude of the last iterate.
+    Magnitude,
+    /// Period of the attracting cycle, one color per hyperbolic component.
+    Period,
+    /// Estimated distance to the boundary of the hyperbolic component, in
+    /// octaves of pixels.
+    Distance,
+    /// Angle of the cycle's multiplier, darkened towards the nucleus of the
+    /// component, which shows every component as a wheel of colors.
+    Multiplier,
+}
+
+impl InteriorMode {
+    pub fn next(self) -> Self {
+        match self {
+            InteriorMode::Black => InteriorMode::Magnitude,
+            InteriorMode::Magnitude => InteriorMode::Period,
+            InteriorMode::Period => InteriorMode::Distance,
+            InteriorMode::Distance => InteriorMode::Multiplier,
+            InteriorMode::Multiplier => InteriorMode::Black,
+        }
+    }
+
+    /// Whether the mode needs interior orbits to be iterated, rather than
+    /// skipped or left at the iteration limit with black.
+    pub fn iterates(&self) -> bool {
+        *self != InteriorMode::Black
+    }
+
+    /// Whether the mode needs the last iterate of every interior point.
+    pub fn uses_last_iterate(&self) -> bool {
+        *self == InteriorMode::Magnitude
+    }
+
+    /// Whether the mode needs the attracting cycle of every interior point.
+    pub fn uses_cycle(&self) -> bool {
+        self.iterates() && !self.uses_last_iterate()
+    }
+}
+
+/// Gradient lengths between the colors of successive periods. The golden
+/// ratio keeps nearby periods far apart on the gradient.
+const PERIOD_STEP: f64 = 0.618_033_988_749_895;
+/// Doublings of the interior distance, in pixels, that span the gradient.
+const INTERIOR_DISTANCE_OCTAVES: f64 = 16.0;
+
+/// Colors the interior point `escape` according to `mode` with `palette`,
+/// for pixels `pixel_size` apart in the plane.
+pub fn interior_to_rgb(
+    escape: Escape,
+    mode: InteriorMode,
+    palette: &Palette,
+    pixel_size: f64,
+) -> [u8; 4] {
+    let cycle = escape.cycle;
+    // Position along the gradient and brightness.
+    let color = match mode {
+        InteriorMode::Black => None,
+        InteriorMode::Magnitude => Some((escape.z.norm() / 2.0, 1.0)),
+        InteriorMode::Period => cycle.map(|cycle| (cycle.period as f64 * PERIOD_STEP, 1.0)),
+        InteriorMode::Distance => cycle.and_then(|cycle| cycle.distance).map(|distance| {
+            let octaves = (distance / pixel_size).max(1.0).log2();
+            (octaves / INTERIOR_DISTANCE_OCTAVES, 1.0)
+        }),
+        InteriorMode::Multiplier => cycle
+            .and_then(|cycle| cycle.multiplier)
+            .map(|multiplier| (multiplier.arg() / TAU, multiplier.norm().min(1.0))),
+    };
+    let Some((position, shade)) = color else {
+        return [0, 0, 0, 255];
+    };
+    let [r, g, b] = palette.at(palette.offset + palette.scale * position);
+    let dim = |c: u8| (c as f64 * shade).round() as u8;
+    [dim(r), dim(g), dim(b), 255]
+}
+
+/// Histogram bins per iteration, as long as there are at most
+/// [`MAX_HISTOGRAM_BINS`] in total.
+const HISTOGRAM_BINS_PER_ITER: usize = 8;
+const MAX_HISTOGRAM_BINS: usize = 1 << 20;
+
+/// Cumulative distribution of smooth escape counts, for histogram
+/// equalization.
+#[derive(Clone, Debug)]
+pub(crate) struct Histogram {
+    /// Bins per unit of the smooth escape count.
+    resolution: f64,
+    /// Fraction of the counts below the start of each bin, one entry past
+    /// the last bin.
+    cumulative: Vec<f64>,
+}
+
+impl Histogram {
+    /// Takes the distribution of `smooth` escape counts up to `max_iters`.
+    pub(crate) fn new(smooth: impl Iterator<Item = f64>, max_iters: usize) -> Self {
+        let bins = (max_iters * HISTOGRAM_BINS_PER_ITER).clamp(1, MAX_HISTOGRAM_BINS);
+        let resolution = bins as f64 / max_iters.max(1) as f64;
+        let mut counts = vec![0_usize; bins];
+        let mut total = 0;
+        for value in smooth {
+            counts[((value * resolution) as usize).min(bins - 1)] += 1;
+            total += 1;
+        }
+        let mut cumulative = Vec::with_capacity(bins + 1);
+        let mut below = 0;
+        cumulative.push(0.0);
+        for count in counts {
+            below += count;
+            cumulative.push(below as f64 / total.max(1) as f64);
+        }
+        Self {
+            resolution,
+            cumulative,
+        }
+    }
+
+    /// Fraction of the counts below `smooth`, interpolated within its bin.
+    pub(crate) fn fraction(&self, smooth: f64) -> f64 {
+        let pos = (smooth * self.resolution).max(0.0);
+        let bin = (pos as usize).min(self.cumulative.len() - 2);
+        let (lo, hi) = (self.cumulative[bin], self.cumulative[bin + 1]);
+        lo + (hi - lo) * (pos - bin as f64).min(1.0)
+    }
+}
+
+/// Converts HSL (degrees, percent, percent) into an opaque RGBA pixel.
+pub fn hsl_to_rgba(h: f64, s: f64, l: f64) -> [u8; 4] {
+    // Normalize HSL values
+    let h_norm = h / 360.0;
+    let s_norm = s / 100.0;
+    let l_norm = l / 100.0;
+
+    // Calculate intermediate values
+    let c = (1.0 - (2.0 * l_norm - 1.0).abs()) * s_norm;
+    let x = c * (1.0 - ((h_norm * 6.0) % 2.0 - 1.0).abs());
+    let m = l_norm - c / 2.0;
+
+    // Derive RGB components
+    let (r, g, b) = if h_norm < 1.0 / 6.0 {
+        (c, x, 0.0)
+    } else if h_norm < 2.0 / 6.0 {
+        (x, c, 0.0)
+    } else if h_norm < 3.0 / 6.0 {
+        (0.0, c, x)
+    } else if h_norm < 4.0 / 6.0 {
+        (0.0, x, c)
+    } else if h_norm < 5.0 / 6.0 {
+        (x, 0.0, c)
+    } else {
+        (c, 0.0, x)
+    };
+
+    // Denormalize and convert to u8
+    let r_u8 = ((r + m) * 255.0) as u8;
+    let g_u8 = ((g + m) * 255.0) as u8;
+    let b_u8 = ((b + m) * 255.0) as u8;
+    let a_u8 = 255; // Alpha (255 means fully opaque)
+
+    [r_u8, g_u8, b_u8, a_u8]
+}
+
+/// Decodes an sRGB channel into linear light in `0..=1`.
+pub(crate) fn srgb_to_linear(c: u8) -> f64 {
+    let c = c as f64 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes linear light into an sRGB channel, clamping out of gamut values.
+pub(crate) fn linear_to_srgb(c: f64) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
+
+/// Converts linear sRGB into OKLab (lightness, green-red, blue-yellow).
+pub(crate) fn linear_to_oklab([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+/// Inverse of [`linear_to_oklab`].
+pub(crate) fn oklab_to_linear([l, a, b]: [f64; 3]) -> [f64; 3] {
+    let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    [
+        4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_,
+        -1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_,
+        -0.0041960863 * l_ - 0.7034186147 * m_ + 1.7076147010 * s_,
+    ]
+}
@@ -0,0 +1,362 @@
+use num::complex::Complex;
+
+use crate::bignum::BigComplex;
+
+/// Escape-time iteration `z -> f(z, c)`.
+///
+/// Implementations only describe a single step; the iteration loop, escape
+/// counting and smoothing live in the kernel.
+pub trait Formula {
+    /// Starting value of the orbit for the parameter `c`.
+    fn initial_z(&self, _c: Complex<f64>) -> Complex<f64> {
+        Complex::new(0.0, 0.0)
+    }
+
+    /// Computes the next orbit value.
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64>;
+
+    /// High precision version of [`step`](Self::step) used for deep zooms.
+    /// Formulas returning `None` are rendered in `f64` at every depth.
+    fn step_big(&self, _z: &BigComplex, _c: &BigComplex) -> Option<BigComplex> {
+        None
+    }
+
+    /// Perturbed version of [`step`](Self::step) used with a reference orbit.
+    ///
+    /// Returns `step(z_ref + dz, c_ref + dc) - step(z_ref, c_ref)`, expanded
+    /// so that no large terms cancel. Formulas returning `None` fall back to
+    /// iterating every pixel with [`step_big`](Self::step_big).
+    fn step_delta(
+        &self,
+        _z_ref: Complex<f64>,
+        _dz: Complex<f64>,
+        _dc: Complex<f64>,
+    ) -> Option<Complex<f64>> {
+        None
+    }
+
+    /// Derivative of [`step`](Self::step) with respect to `z`, used to track
+    /// the derivative of the orbit for distance estimation. Formulas that are
+    /// not holomorphic in `z` return `None` and get no distance estimate.
+    fn derivative(&self, _z: Complex<f64>) -> Option<Complex<f64>> {
+        None
+    }
+
+    /// Second derivative of [`step`](Self::step) with respect to `z`, used
+    /// for the interior distance estimate. `None` where
+    /// [`derivative`](Self::derivative) is.
+    fn second_derivative(&self, _z: Complex<f64>) -> Option<Complex<f64>> {
+        None
+    }
+
+    /// Returns true once `z` is known to diverge.
+    fn escaped(&self, z: Complex<f64>) -> bool {
+        z.norm_sqr() > 4.0
+    }
+
+    /// Growth rate of `|z|` far from the origin, used to smooth the escape
+    /// count.
+    fn degree(&self) -> f64 {
+        2.0
+    }
+
+    /// Returns true if the parameter `c` is cheaply known to lie inside the
+    /// set, so parameter-plane pixels can skip iterating it.
+    fn known_interior(&self, _c: Complex<f64>) -> bool {
+        false
+    }
+
+    /// Whether [`step`](Self::step) is exactly `z^2 + c` with the default
+    /// escape test, which the kernel iterates for several pixels at once.
+    fn quadratic(&self) -> bool {
+        false
+    }
+}
+
+/// The classic `z^2 + c`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mandelbrot;
+
+impl Formula for Mandelbrot {
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        z * z + c
+    }
+
+    fn step_big(&self, z: &BigComplex, c: &BigComplex) -> Option<BigComplex> {
+        Some(&z.square() + c)
+    }
+
+    fn step_delta(
+        &self,
+        z_ref: Complex<f64>,
+        dz: Complex<f64>,
+        dc: Complex<f64>,
+    ) -> Option<Complex<f64>> {
+        Some((2.0 * z_ref + dz) * dz + dc)
+    }
+
+    fn derivative(&self, z: Complex<f64>) -> Option<Complex<f64>> {
+        Some(2.0 * z)
+    }
+
+    fn second_derivative(&self, _z: Complex<f64>) -> Option<Complex<f64>> {
+        Some(Complex::new(2.0, 0.0))
+    }
+
+    /// Main cardioid and period-2 bulb, which together cover most of the
+    /// interior of the default view.
+    fn known_interior(&self, c: Complex<f64>) -> bool {
+        let x = c.re - 0.25;
+        let y2 = c.im * c.im;
+        let q = x * x + y2;
+        let in_cardioid = q * (q + x) <= 0.25 * y2;
+        let in_bulb = (c.re + 1.0) * (c.re + 1.0) + y2 <= 0.0625;
+        in_cardioid || in_bulb
+    }
+
+    fn quadratic(&self) -> bool {
+        true
+    }
+}
+
+/// `(|Re z| + i|Im z|)^2 + c`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BurningShip;
+
+impl Formula for BurningShip {
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        let z = Complex::new(z.re.abs(), z.im.abs());
+        z * z + c
+    }
+
+    fn step_big(&self, z: &BigComplex, c: &BigComplex) -> Option<BigComplex> {
+        let z = BigComplex::new(z.re.abs(), z.im.abs());
+        Some(&z.square() + c)
+    }
+}
+
+/// Tricorn, also known as the Mandelbar set: `conj(z)^2 + c`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tricorn;
+
+impl Formula for Tricorn {
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        let z = z.conj();
+        z * z + c
+    }
+
+    fn step_big(&self, z: &BigComplex, c: &BigComplex) -> Option<BigComplex> {
+        Some(&z.conj().square() + c)
+    }
+
+    fn step_delta(
+        &self,
+        z_ref: Complex<f64>,
+        dz: Complex<f64>,
+        dc: Complex<f64>,
+    ) -> Option<Complex<f64>> {
+        Some(((2.0 * z_ref + dz) * dz).conj() + dc)
+    }
+}
+
+/// `z^power + c` for a real `power` greater than 1.
+#[derive(Clone, Copy, Debug)]
+pub struct Multibrot {
+    pub power: f64,
+}
+
+impl Formula for Multibrot {
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        if self.power.fract() == 0.0 && self.power.abs() <= i32::MAX as f64 {
+            z.powi(self.power as i32) + c
+        } else if z == Complex::new(0.0, 0.0) {
+            // `powf` goes through `ln(0)` and would produce NaN.
+            c
+        } else {
+            z.powf(self.power) + c
+        }
+    }
+
+    fn step_big(&self, z: &BigComplex, c: &BigComplex) -> Option<BigComplex> {
+        // Only integer powers have an exact fixed-point expansion.
+        if self.power.fract() != 0.0 || !(2.0..=64.0).contains(&self.power) {
+            return None;
+        }
+        let mut w = z.clone();
+        for _ in 1..self.power as u32 {
+            w = &w * z;
+        }
+        Some(&w + c)
+    }
+
+    fn derivative(&self, z: Complex<f64>) -> Option<Complex<f64>> {
+        if z == Complex::new(0.0, 0.0) {
+            // The power is above 1, so the derivative vanishes at 0.
+            return Some(z);
+        }
+        Some(self.power * pow(z, self.power - 1.0))
+    }
+
+    fn second_derivative(&self, z: Complex<f64>) -> Option<Complex<f64>> {
+        let n = self.power - 2.0;
+        if z == Complex::new(0.0, 0.0) {
+            // Vanishes at 0 for powers above 2 and diverges below.
+            return match n {
+                0.0 => Some(Complex::new(2.0, 0.0)),
+                n if n > 0.0 => Some(z),
+                _ => None,
+            };
+        }
+        Some(self.power * (self.power - 1.0) * pow(z, n))
+    }
+
+    /// `|z| > 2` only guarantees divergence for powers of at least 2. Below,
+    /// orbits diverge once `|z|^(power - 1) > 2` as well.
+    fn escaped(&self, z: Complex<f64>) -> bool {
+        if self.power >= 2.0 {
+            return z.norm_sqr() > 4.0;
+        }
+        z.norm_sqr() > f64::powf(2.0, 2.0 / (self.power - 1.0))
+    }
+
+    fn degree(&self) -> f64 {
+        self.power
+    }
+}
+
+/// `z^n` for a nonzero `z`, exact for integer `n`.
+fn pow(z: Complex<f64>, n: f64) -> Complex<f64> {
+    if n.fract() == 0.0 && n.abs() <= i32::MAX as f64 {
+        z.powi(n as i32)
+    } else {
+        z.powf(n)
+    }
+}
+
+/// Celtic Mandelbrot: `|Re(z^2)| + i Im(z^2) + c`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Celtic;
+
+impl Formula for Celtic {
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        let z2 = z * z;
+        Complex::new(z2.re.abs(), z2.im) + c
+    }
+
+    fn step_big(&self, z: &BigComplex, c: &BigComplex) -> Option<BigComplex> {
+        let z2 = z.square();
+        Some(&BigComplex::new(z2.re.abs(), z2.im) + c)
+    }
+}
+
+/// Runtime choice between the built-in formulas.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Fractal {
+    #[default]
+    Mandelbrot,
+    BurningShip,
+    Tricorn,
+    /// `z^n + c`, see [`Multibrot`].
+    Multibrot(f64),
+    Celtic,
+}
+
+impl Fractal {
+    /// Power used when cycling onto [`Fractal::Multibrot`].
+    pub const DEFAULT_MULTIBROT_POWER: f64 = 3.0;
+
+    pub fn next(self) -> Self {
+        match self {
+            Fractal::Mandelbrot => Fractal::BurningShip,
+            Fractal::BurningShip => Fractal::Tricorn,
+            Fractal::Tricorn => Fractal::Multibrot(Self::DEFAULT_MULTIBROT_POWER),
+            Fractal::Multibrot(_) => Fractal::Celtic,
+            Fractal::Celtic => Fractal::Mandelbrot,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Fractal::Mandelbrot => "mandelbrot".to_string(),
+            Fractal::BurningShip => "burning-ship".to_string(),
+            Fractal::Tricorn => "tricorn".to_string(),
+            Fractal::Multibrot(power) => format!("multibrot:{power}"),
+            Fractal::Celtic => "celtic".to_string(),
+        }
+    }
+}
+
+/// Evaluates `$call` with `$formula` bound to the formula `$fractal` selects.
+macro_rules! delegate {
+    ($fractal:expr, $formula:ident => $call:expr) => {
+        match *$fractal {
+            Fractal::Mandelbrot => {
+                let $formula = Mandelbrot;
+                $call
+            }
+            Fractal::BurningShip => {
+                let $formula = BurningShip;
+                $call
+            }
+            Fractal::Tricorn => {
+                let $formula = Tricorn;
+                $call
+            }
+            Fractal::Multibrot(power) => {
+                let $formula = Multibrot { power };
+                $call
+            }
+            Fractal::Celtic => {
+                let $formula = Celtic;
+                $call
+            }
+        }
+    };
+}
+
+impl Formula for Fractal {
+    fn initial_z(&self, c: Complex<f64>) -> Complex<f64> {
+        delegate!(self, formula => formula.initial_z(c))
+    }
+
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        delegate!(self, formula => formula.step(z, c))
+    }
+
+    fn step_big(&self, z: &BigComplex, c: &BigComplex) -> Option<BigComplex> {
+        delegate!(self, formula => formula.step_big(z, c))
+    }
+
+    fn step_delta(
+        &self,
+        z_ref: Complex<f64>,
+        dz: Complex<f64>,
+        dc: Complex<f64>,
+    ) -> Option<Complex<f64>> {
+        delegate!(self, formula => formula.step_delta(z_ref, dz, dc))
+    }
+
+    fn derivative(&self, z: Complex<f64>) -> Option<Complex<f64>> {
+        delegate!(self, formula => formula.derivative(z))
+    }
+
+    fn second_derivative(&self, z: Complex<f64>) -> Option<Complex<f64>> {
+        delegate!(self, formula => formula.second_derivative(z))
+    }
+
+    fn escaped(&self, z: Complex<f64>) -> bool {
+        delegate!(self, formula => formula.escaped(z))
+    }
+
+    fn degree(&self) -> f64 {
+        delegate!(self, formula => formula.degree())
+    }
+
+    fn known_interior(&self, c: Complex<f64>) -> bool {
+        delegate!(self, formula => formula.known_interior(c))
+    }
+
+    fn quadratic(&self) -> bool {
+        delegate!(self, formula => formula.quadratic())
+    }
+}
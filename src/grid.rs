@@ -0,0 +1,998 @@
+use num::complex::Complex;
+use rayon::prelude::*;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::bignum::BigComplex;
+use crate::color::{escape_to_rgb, interior_to_rgb, ColorMode, Histogram, InteriorMode};
+use crate::formula::{BurningShip, Celtic, Formula, Fractal, Mandelbrot, Multibrot, Tricorn};
+use crate::kernel::{
+    iterate, iterate_big, iterate_parameter, supports_big, supports_perturbation, Average, Cycle,
+    Derivative, Escape, Shortcut, Statistics, MAX_ITERS,
+};
+use crate::lighting::Lighting;
+use crate::palette::Palette;
+use crate::perturbation::ReferenceOrbit;
+use crate::simd::{escape_lanes, simd_compiled, LANES};
+use crate::subdivide::subdivide;
+use crate::supersampling::{self, Supersampling};
+use crate::trap::OrbitTrap;
+use crate::viewport::Viewport;
+
+/// Iterations added per doubling of the zoom factor in auto mode.
+const AUTO_ITERS_PER_OCTAVE: f64 = 50.0;
+/// Distance in pixels from a whole number of pixels up to which a pan is
+/// still treated as pixel aligned, which absorbs rounding of the center.
+const PAN_ALIGNMENT_TOLERANCE: f64 = 1e-6;
+/// Histogram coloring keeps its distribution while the view is only panned
+/// by less than this fraction of the extent, so navigating doesn't flicker.
+const HISTOGRAM_PAN_TOLERANCE: f64 = 0.5;
+/// Pixels handed to a rayon task at once, enough to fill the SIMD lanes
+/// several times over.
+pub(crate) const BATCH: usize = 64;
+/// Pixels evaluated before their escapes are stored into the cells and
+/// statistic planes, which bounds the memory the escapes take meanwhile.
+const TILE: usize = 1 << 14;
+
+/// Timing information returned by [`MandelbrotGrid::update`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UpdateStats {
+    pub elapsed: Duration,
+    /// Iteration limit the update ran with.
+    pub max_iters: usize,
+    /// How the pixels were iterated.
+    pub precision: Precision,
+    /// Length of the reference orbit in perturbation mode.
+    pub reference_len: usize,
+    /// Pixels that were rebased onto the start of the reference orbit to
+    /// avoid perturbation glitches.
+    pub rebased_pixels: usize,
+    /// Pixels skipped by [`Formula::known_interior`], such as the main
+    /// cardioid and period-2 bulb of the Mandelbrot set.
+    pub known_interior_pixels: usize,
+    /// Pixels stopped early because their orbit became periodic.
+    pub periodic_pixels: usize,
+    /// Pixels filled from the border of a uniformly escaping rectangle by
+    /// [`Strategy::Subdivide`].
+    pub filled_pixels: usize,
+    /// Pixels kept from the previous update because the view was only
+    /// panned, see [`MandelbrotGrid::reusable_shift`].
+    pub reused_pixels: usize,
+    /// Pixels colored from several samples, see
+    /// [`MandelbrotGrid::supersampling`].
+    pub supersampled_pixels: usize,
+}
+
+impl fmt::Display for UpdateStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Update elapsed: {:?} (max iters: {}, skipped: {} cardioid/bulb, {} periodic, {} filled, {} reused",
+            self.elapsed,
+            self.max_iters,
+            self.known_interior_pixels,
+            self.periodic_pixels,
+            self.filled_pixels,
+            self.reused_pixels
+        )?;
+        match self.precision {
+            Precision::Double => {}
+            Precision::Perturbation => write!(
+                f,
+                ", perturbation: reference orbit {} iters, {} rebased pixels",
+                self.reference_len, self.rebased_pixels
+            )?,
+            Precision::Arbitrary => write!(f, ", arbitrary precision")?,
+        }
+        if self.supersampled_pixels > 0 {
+            write!(f, ", {} supersampled", self.supersampled_pixels)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Arithmetic used by an update.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Precision {
+    /// Plain `f64` per pixel.
+    #[default]
+    Double,
+    /// `f64` deltas against an arbitrary-precision reference orbit.
+    Perturbation,
+    /// Arbitrary precision per pixel.
+    Arbitrary,
+}
+
+/// Order in which [`MandelbrotGrid::update`] visits the pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// Mariani–Silver subdivision: rectangles whose border escaped
+    /// uniformly are filled without iterating their inside. Only escape
+    /// counts are uniform across a rectangle, so this only applies to
+    /// [`ColorMode::Banded`] without lighting or interior coloring; other
+    /// colorings iterate every pixel.
+    #[default]
+    Subdivide,
+    /// Iterate every pixel.
+    Full,
+}
+
+/// Iteration data of one pixel. The colors are kept in a separate RGBA
+/// plane, see [`MandelbrotGrid::rgba`], and the orbit statistics in planes
+/// of their own that are only allocated while the coloring needs them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cell {
+    pub steps: usize,
+    /// Continuous escape count, see [`crate::Escape::smooth`].
+    pub smooth: f64,
+}
+
+impl From<Escape> for Cell {
+    fn from(escape: Escape) -> Self {
+        Self {
+            steps: escape.steps,
+            smooth: escape.smooth,
+        }
+    }
+}
+
+/// Orbit statistics of every cell, one plane per statistic. A plane is only
+/// allocated if the cells were computed with the statistic, see
+/// [`Computed`].
+#[derive(Clone, Debug, Default)]
+struct Planes {
+    /// Last iterates, see [`crate::Escape::z`].
+    z: Option<Vec<Complex<f64>>>,
+    /// Distance estimates, see [`crate::Escape::distance`].
+    distance: Option<Vec<Option<f64>>>,
+    /// Surface normals, see [`crate::Escape::normal`].
+    normal: Option<Vec<Option<Complex<f64>>>>,
+    /// Closest approaches to the orbit trap, see [`crate::Escape::trap`].
+    trap: Option<Vec<Option<f64>>>,
+    /// Orbit averages, see [`crate::Escape::average`].
+    average: Option<Vec<Option<f64>>>,
+    /// Attracting cycles, see [`crate::Escape::cycle`].
+    cycle: Option<Vec<Option<Cycle>>>,
+}
+
+impl Planes {
+    /// Planes for `len` cells holding the statistics of `computed`.
+    fn new(len: usize, computed: &Computed) -> Self {
+        fn plane<T: Clone + Default>(stored: bool, len: usize) -> Option<Vec<T>> {
+            stored.then(|| vec![T::default(); len])
+        }
+        Self {
+            z: plane(computed.z, len),
+            distance: plane(computed.derivative, len),
+            normal: plane(computed.derivative, len),
+            trap: plane(computed.trap.is_some(), len),
+            average: plane(computed.average.is_some(), len),
+            cycle: plane(computed.cycle, len),
+        }
+    }
+
+    /// Stores the statistics of `escape` for cell `idx`.
+    fn set(&mut self, idx: usize, escape: &Escape) {
+        fn set<T>(plane: &mut Option<Vec<T>>, idx: usize, value: T) {
+            if let Some(plane) = plane {
+                plane[idx] = value;
+            }
+        }
+        set(&mut self.z, idx, escape.z);
+        set(&mut self.distance, idx, escape.distance);
+        set(&mut self.normal, idx, escape.normal);
+        set(&mut self.trap, idx, escape.trap);
+        set(&mut self.average, idx, escape.average);
+        set(&mut self.cycle, idx, escape.cycle);
+    }
+
+    /// Copies the statistics of cell `src` of `other` to cell `idx`, for the
+    /// planes both hold.
+    fn copy(&mut self, idx: usize, other: &Self, src: usize) {
+        fn copy<T: Copy>(
+            plane: &mut Option<Vec<T>>,
+            other: &Option<Vec<T>>,
+            idx: usize,
+            src: usize,
+        ) {
+            if let (Some(plane), Some(other)) = (plane, other) {
+                plane[idx] = other[src];
+            }
+        }
+        copy(&mut self.z, &other.z, idx, src);
+        copy(&mut self.distance, &other.distance, idx, src);
+        copy(&mut self.normal, &other.normal, idx, src);
+        copy(&mut self.trap, &other.trap, idx, src);
+        copy(&mut self.average, &other.average, idx, src);
+        copy(&mut self.cycle, &other.cycle, idx, src);
+    }
+
+    /// Escape of `cell` at `idx` with the statistics the planes hold.
+    fn escape(&self, idx: usize, cell: Cell) -> Escape {
+        fn get<T: Copy + Default>(plane: &Option<Vec<T>>, idx: usize) -> T {
+            plane.as_ref().map_or_else(T::default, |plane| plane[idx])
+        }
+        Escape {
+            steps: cell.steps,
+            smooth: cell.smooth,
+            z: get(&self.z, idx),
+            distance: get(&self.distance, idx),
+            normal: get(&self.normal, idx),
+            trap: get(&self.trap, idx),
+            average: get(&self.average, idx),
+            cycle: get(&self.cycle, idx),
+            shortcut: None,
+        }
+    }
+}
+
+/// View and settings the cells were last computed for.
+#[derive(Clone, Debug, PartialEq)]
+struct Computed {
+    viewport: Viewport,
+    max_iters: usize,
+    formula: Fractal,
+    julia: Option<(f64, f64)>,
+    /// Whether the cells carry distance estimates and normals.
+    derivative: bool,
+    /// Trap the cells recorded the closest approach to, if any.
+    trap: Option<OrbitTrap>,
+    /// Orbit average the cells carry, if any.
+    average: Option<Average>,
+    /// Whether the cells carry the last iterate of interior points.
+    z: bool,
+    /// Whether the cells carry the attracting cycle of interior points.
+    cycle: bool,
+    /// Whether cells may have been filled from the border of their
+    /// rectangle, which leaves only their escape counts exact.
+    filled: bool,
+}
+
+/// Settings the RGBA plane was colored with.
+#[derive(Clone, Debug, PartialEq)]
+struct Coloring {
+    mode: ColorMode,
+    palette: Palette,
+    lighting: Option<Lighting>,
+    interior: InteriorMode,
+    interior_palette: Palette,
+    supersampling: Option<Supersampling>,
+}
+
+/// Pixel grid holding the escape count and color of every pixel of `viewport`,
+/// as an iteration plane and an RGBA plane.
+#[derive(Clone, Debug)]
+pub struct MandelbrotGrid {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    planes: Planes,
+    rgba: Vec<u8>,
+    pub viewport: Viewport,
+    /// Iteration limit used for every pixel, or the base limit when
+    /// `auto_iters` is set.
+    pub max_iters: usize,
+    /// Raise the limit with the log of the zoom factor, see
+    /// [`MandelbrotGrid::iteration_limit`].
+    pub auto_iters: bool,
+    pub color_mode: ColorMode,
+    /// Gradient the cells are colored with, see [`recolor`](Self::recolor).
+    pub palette: Palette,
+    /// Light shining onto the image, if any.
+    pub lighting: Option<Lighting>,
+    /// Trap used by [`ColorMode::Trap`].
+    pub trap: OrbitTrap,
+    /// Coloring of the points that never escape.
+    pub interior: InteriorMode,
+    /// Gradient the interior is colored with.
+    pub interior_palette: Palette,
+    /// Antialiasing of the RGBA plane, if any.
+    pub supersampling: Option<Supersampling>,
+    /// Formula used by [`update`](Self::update).
+    pub formula: Fractal,
+    /// Render the Julia set for this parameter instead of the parameter plane.
+    pub julia: Option<(f64, f64)>,
+    /// How pixels are visited by [`update`](Self::update).
+    pub strategy: Strategy,
+    computed: Option<Computed>,
+    /// Coloring the RGBA plane was last filled with.
+    colored: Option<Coloring>,
+    /// Distribution used by [`ColorMode::Histogram`] and the view it was
+    /// taken for.
+    histogram: Option<(Computed, Histogram)>,
+}
+impl MandelbrotGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        let size = width.checked_mul(height).expect("too big");
+        Self {
+            width,
+            height,
+            cells: vec![Cell::default(); size],
+            planes: Planes::default(),
+            rgba: vec![0; size.checked_mul(4).expect("too big")],
+            viewport: Viewport::default(),
+            max_iters: MAX_ITERS,
+            auto_iters: false,
+            color_mode: ColorMode::default(),
+            palette: Palette::default(),
+            lighting: None,
+            trap: OrbitTrap::default(),
+            interior: InteriorMode::default(),
+            interior_palette: Palette::default(),
+            supersampling: None,
+            formula: Fractal::default(),
+            julia: None,
+            strategy: Strategy::default(),
+            computed: None,
+            colored: None,
+            histogram: None,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns a grid of another size with the same view and settings, whose
+    /// cells are yet to be computed.
+    pub fn resized(&self, width: usize, height: usize) -> Self {
+        let mut grid = Self::new(width, height);
+        grid.copy_settings(self);
+        grid
+    }
+
+    /// Takes over the view and settings of `other` but keeps the cells, so
+    /// the next update can reuse them if the view was only panned.
+    pub fn copy_settings(&mut self, other: &Self) {
+        self.viewport = other.viewport.clone();
+        self.max_iters = other.max_iters;
+        self.auto_iters = other.auto_iters;
+        self.color_mode = other.color_mode;
+        self.palette = other.palette.clone();
+        self.lighting = other.lighting;
+        self.trap = other.trap;
+        self.interior = other.interior;
+        self.interior_palette = other.interior_palette.clone();
+        self.supersampling = other.supersampling;
+        self.formula = other.formula;
+        self.julia = other.julia;
+        self.strategy = other.strategy;
+    }
+
+    /// Cells in row-major order.
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    /// Colors of the cells as tightly packed RGBA, in row-major order.
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// Iteration limit the next [`update`](Self::update) will use.
+    ///
+    /// In auto mode this is `max_iters` plus a fixed number of iterations
+    /// for every doubling of the zoom factor, so deep views keep their detail.
+    pub fn iteration_limit(&self) -> usize {
+        if !self.auto_iters {
+            return self.max_iters;
+        }
+        let octaves = self.viewport.zoom_factor().log2().max(0.0);
+        self.max_iters + (octaves * AUTO_ITERS_PER_OCTAVE) as usize
+    }
+
+    /// Offset in whole pixels by which the viewport moved since the cells were
+    /// computed, if nothing else changed.
+    ///
+    /// The next update then shifts the cells by this offset and only computes
+    /// the strips that came into view.
+    pub fn reusable_shift(&self) -> Option<(isize, isize)> {
+        let computed = self.computed.as_ref()?;
+        let old = &computed.viewport;
+        let new = &self.viewport;
+        // Cells with more statistics than needed serve every coloring.
+        let unchanged = Computed {
+            viewport: old.clone(),
+            derivative: computed.derivative || self.tracks_derivative(),
+            trap: self.traps().or(computed.trap),
+            average: self.color_mode.average().or(computed.average),
+            z: computed.z || self.interior.uses_last_iterate(),
+            cycle: computed.cycle || self.interior.uses_cycle(),
+            filled: computed.filled && self.fills(),
+            ..self.computed_for(self.iteration_limit())
+        };
+        if unchanged != *computed || old.extent_x != new.extent_x || old.extent_y != new.extent_y {
+            return None;
+        }
+        let dx = (&new.center_re - &old.center_re).to_f64() * self.width as f64 / new.extent_x;
+        let dy = (&new.center_im - &old.center_im).to_f64() * self.height as f64 / new.extent_y;
+        let (sx, sy) = (dx.round(), dy.round());
+        let aligned =
+            (dx - sx).abs() < PAN_ALIGNMENT_TOLERANCE && (dy - sy).abs() < PAN_ALIGNMENT_TOLERANCE;
+        let overlaps = sx.abs() < self.width as f64 && sy.abs() < self.height as f64;
+        (aligned && overlaps).then_some((sx as isize, sy as isize))
+    }
+
+    fn computed_for(&self, max_iters: usize) -> Computed {
+        Computed {
+            viewport: self.viewport.clone(),
+            max_iters,
+            formula: self.formula,
+            julia: self.julia,
+            derivative: self.tracks_derivative(),
+            trap: self.traps(),
+            average: self.color_mode.average(),
+            z: self.interior.uses_last_iterate(),
+            cycle: self.interior.uses_cycle(),
+            filled: self.fills(),
+        }
+    }
+
+    fn coloring(&self) -> Coloring {
+        Coloring {
+            mode: self.color_mode,
+            palette: self.palette.clone(),
+            lighting: self.lighting,
+            interior: self.interior,
+            interior_palette: self.interior_palette.clone(),
+            supersampling: self.supersampling,
+        }
+    }
+
+    /// Trap whose closest approach the coloring needs, if any.
+    fn traps(&self) -> Option<OrbitTrap> {
+        self.color_mode.uses_trap().then_some(self.trap)
+    }
+
+    /// Whether [`Strategy::Subdivide`] may fill rectangles, which it only
+    /// does if the coloring reads nothing of the cells but their escape
+    /// counts.
+    fn fills(&self) -> bool {
+        self.strategy == Strategy::Subdivide
+            && self.color_mode == ColorMode::Banded
+            && self.lighting.is_none()
+            && !self.interior.iterates()
+    }
+
+    /// Whether the coloring needs the derivative of every orbit.
+    fn tracks_derivative(&self) -> bool {
+        self.color_mode.uses_distance() || self.lighting.is_some()
+    }
+
+    /// Index of the cell `shift` pixels away from cell `idx`, if it lies on
+    /// the grid.
+    fn shifted_index(&self, idx: usize, shift: (isize, isize)) -> Option<usize> {
+        shifted_index(self.width, self.height, idx, shift)
+    }
+
+    /// Switches to `palette` and recolors every cell without iterating
+    /// again. Changing `color_mode` or `lighting` only recolors on the next
+    /// update as well. Supersampled pixels are left with a single sample
+    /// until the next update, which samples them again.
+    pub fn recolor(&mut self, palette: &Palette) {
+        self.palette = palette.clone();
+        if let Some(computed) = &self.computed {
+            let max_iters = computed.max_iters;
+            if let Some(histogram) = self.take_histogram(&self.cells, max_iters) {
+                self.histogram = Some(histogram);
+            }
+            self.color(max_iters);
+        }
+    }
+
+    /// Takes the distribution of the exterior `cells` for histogram
+    /// coloring, unless the view was only panned a little since it was last
+    /// taken. A new distribution changes the color of every cell.
+    fn take_histogram(&self, cells: &[Cell], max_iters: usize) -> Option<(Computed, Histogram)> {
+        if self.color_mode != ColorMode::Histogram {
+            return None;
+        }
+        let current = self.computed_for(max_iters);
+        if let Some((taken_for, _)) = &self.histogram {
+            let (old, new) = (&taken_for.viewport, &current.viewport);
+            let settings = Computed {
+                viewport: old.clone(),
+                ..current.clone()
+            };
+            if settings == *taken_for
+                && old.extent_x == new.extent_x
+                && old.extent_y == new.extent_y
+            {
+                let dx = (&new.center_re - &old.center_re).to_f64() / new.extent_x;
+                let dy = (&new.center_im - &old.center_im).to_f64() / new.extent_y;
+                if dx.abs() < HISTOGRAM_PAN_TOLERANCE && dy.abs() < HISTOGRAM_PAN_TOLERANCE {
+                    return None;
+                }
+            }
+        }
+        let exterior = cells.iter().filter(|cell| cell.steps < max_iters);
+        let histogram = Histogram::new(exterior.map(|cell| cell.smooth), max_iters);
+        Some((current, histogram))
+    }
+
+    /// Fills the RGBA plane from the cells with the current coloring.
+    fn color(&mut self, max_iters: usize) {
+        let coloring = self.coloring();
+        let pixel_size = self.viewport.extent_x / self.width as f64;
+        let histogram = match (coloring.mode, &self.histogram) {
+            (ColorMode::Histogram, Some((_, histogram))) => Some(histogram),
+            _ => None,
+        };
+        let planes = &self.planes;
+        self.rgba
+            .par_chunks_exact_mut(4)
+            .zip(self.cells.par_iter())
+            .enumerate()
+            .for_each(|(idx, (pix, &cell))| {
+                let escape = planes.escape(idx, cell);
+                let rgba = sample_color(escape, &coloring, histogram, max_iters, pixel_size);
+                pix.copy_from_slice(&rgba);
+            });
+        // The pixels hold a single sample until they are supersampled.
+        self.colored = Some(Coloring {
+            supersampling: None,
+            ..coloring
+        });
+    }
+
+    /// Recomputes every cell for the current viewport with `self.formula`.
+    pub fn update(&mut self) -> UpdateStats {
+        self.update_cancellable(&AtomicBool::new(false))
+            .expect("update was not cancelled")
+    }
+
+    /// Like [`update`](Self::update), but gives up as soon as `cancel` is
+    /// set, for example because the view changed while rendering in the
+    /// background. Returns `None` and leaves the cells and their colors
+    /// untouched if it was cancelled.
+    pub fn update_cancellable(&mut self, cancel: &AtomicBool) -> Option<UpdateStats> {
+        // Dispatch once here so the per-pixel loop is monomorphized.
+        match self.formula {
+            Fractal::Mandelbrot => self.render(&Mandelbrot, cancel),
+            Fractal::BurningShip => self.render(&BurningShip, cancel),
+            Fractal::Tricorn => self.render(&Tricorn, cancel),
+            Fractal::Multibrot(power) => self.render(&Multibrot { power }, cancel),
+            Fractal::Celtic => self.render(&Celtic, cancel),
+        }
+    }
+
+    /// Recomputes every cell for the current viewport with `formula`,
+    /// ignoring `self.formula`.
+    ///
+    /// Once neighbouring pixels can no longer be told apart in `f64`, pixels
+    /// are iterated as `f64` perturbations of an arbitrary-precision
+    /// reference orbit through the viewport center. Formulas without
+    /// [`Formula::step_delta`] iterate every pixel in arbitrary precision
+    /// instead, if they support it.
+    pub fn update_with<F: Formula + Sync + ?Sized>(&mut self, formula: &F) -> UpdateStats {
+        let stats = self
+            .render(formula, &AtomicBool::new(false))
+            .expect("update was not cancelled");
+        // The cells were not computed with `self.formula`, so the next update
+        // can't reuse them or their distribution.
+        self.computed = None;
+        self.histogram = None;
+        stats
+    }
+
+    fn render<F: Formula + Sync + ?Sized>(
+        &mut self,
+        formula: &F,
+        cancel: &AtomicBool,
+    ) -> Option<UpdateStats> {
+        let start_time = Instant::now();
+        let max_iters = self.iteration_limit();
+        let shift = self.reusable_shift();
+        let precision = if !self.viewport.needs_high_precision(self.width, self.height) {
+            Precision::Double
+        } else if supports_perturbation(formula) && supports_big(formula) {
+            Precision::Perturbation
+        } else if supports_big(formula) {
+            Precision::Arbitrary
+        } else {
+            Precision::Double
+        };
+        let frac_bits = self.viewport.precision_bits();
+        let (cx, cy) = self.viewport.center();
+        let (viewport, width, height) = (self.viewport.clone(), self.width, self.height);
+        let julia = self.julia.map(|(re, im)| Complex::new(re, im));
+        let statistics = Statistics {
+            derivative: self.tracks_derivative().then_some(match julia {
+                Some(_) => Derivative::Initial,
+                None => Derivative::Parameter,
+            }),
+            trap: self.traps(),
+            average: self.color_mode.average(),
+            cycle: self.interior.iterates().then_some(match julia {
+                Some(_) => Derivative::Initial,
+                None => Derivative::Parameter,
+            }),
+        };
+        let reference = (precision == Precision::Perturbation).then(|| {
+            let center = BigComplex::new(
+                self.viewport.center_re.clone(),
+                self.viewport.center_im.clone(),
+            );
+            let (z, c) = match julia {
+                Some(c) => (center, BigComplex::from_f64(c, frac_bits)),
+                None => {
+                    let z = formula.initial_z(center.to_f64());
+                    (BigComplex::from_f64(z, frac_bits), center)
+                }
+            };
+            ReferenceOrbit::compute(formula, z, &c, max_iters)
+                .expect("formula supports high precision")
+        });
+
+        // Evaluates the fractional pixel `(x, y)`, the center of pixel
+        // `(i, j)` being `(i + 0.5, j + 0.5)`.
+        let eval = |x: f64, y: f64| {
+            // Skip the remaining pixels once cancelled; the result is
+            // discarded anyway.
+            if cancel.load(Ordering::Relaxed) {
+                return (Escape::default(), 0);
+            }
+            let (dx, dy) = viewport.pixel_offset(x, y, width, height);
+            let offset = Complex::new(dx, dy);
+            let mut rebases = 0;
+            let escape = match precision {
+                Precision::Double => {
+                    let point = Complex::new(cx + dx, cy + dy);
+                    match julia {
+                        Some(c) => iterate(formula, point, c, max_iters, statistics),
+                        None => iterate_parameter(formula, point, max_iters, statistics),
+                    }
+                }
+                Precision::Perturbation => {
+                    let reference = reference.as_ref().expect("reference orbit");
+                    let (dz, dc) = match julia {
+                        Some(_) => (offset, Complex::new(0.0, 0.0)),
+                        None => (Complex::new(0.0, 0.0), offset),
+                    };
+                    match reference.escape_tracked(formula, dz, dc, max_iters, statistics) {
+                        Some((escape, n)) => {
+                            rebases = n;
+                            escape
+                        }
+                        // The reference escaped within a step, so every
+                        // pixel of the view escapes almost immediately and
+                        // `f64` resolves them well enough.
+                        None => {
+                            let point = Complex::new(cx + dx, cy + dy);
+                            match julia {
+                                Some(c) => iterate(formula, point, c, max_iters, statistics),
+                                None => {
+                                    let z = formula.initial_z(point);
+                                    iterate(formula, z, point, max_iters, statistics)
+                                }
+                            }
+                        }
+                    }
+                }
+                Precision::Arbitrary => {
+                    let point = viewport.subpixel_to_big(x, y, width, height, frac_bits);
+                    let (z, c) = match julia {
+                        Some(c) => (point, BigComplex::from_f64(c, frac_bits)),
+                        None => {
+                            let z = formula.initial_z(point.to_f64());
+                            (BigComplex::from_f64(z, frac_bits), point)
+                        }
+                    };
+                    iterate_big(formula, z, &c, max_iters, statistics)
+                        .expect("formula supports high precision")
+                }
+            };
+            (escape, rebases)
+        };
+
+        // Quadratic formulas in f64 iterate several pixels at once with SIMD,
+        // which records no statistics.
+        let vectorize = precision == Precision::Double
+            && statistics == Statistics::default()
+            && formula.quadratic()
+            && simd_compiled();
+        let eval_batch = |pixels: &[(usize, usize)], out: &mut [(Escape, usize)]| {
+            if !vectorize || cancel.load(Ordering::Relaxed) {
+                for (&(x, y), res) in pixels.iter().zip(out) {
+                    *res = eval(x as f64 + 0.5, y as f64 + 0.5);
+                }
+                return;
+            }
+            let zero = Complex::new(0.0, 0.0);
+            let mut points = [(0, zero, zero); BATCH];
+            let mut count = 0;
+            for (i, &(x, y)) in pixels.iter().enumerate() {
+                let (x, y) = (x as f64 + 0.5, y as f64 + 0.5);
+                let (dx, dy) = viewport.pixel_offset(x, y, width, height);
+                let point = Complex::new(cx + dx, cy + dy);
+                points[count] = match julia {
+                    Some(c) => (i, point, c),
+                    // Known interior points would keep their lane group
+                    // iterating to `max_iters`, so leave them to `eval`.
+                    None if formula.known_interior(point) => {
+                        out[i] = eval(x, y);
+                        continue;
+                    }
+                    None => (i, formula.initial_z(point), point),
+                };
+                count += 1;
+            }
+            let mut groups = points[..count].chunks_exact(LANES);
+            for group in &mut groups {
+                let z = std::array::from_fn(|lane| group[lane].1);
+                let c = std::array::from_fn(|lane| group[lane].2);
+                for (&(i, _, _), escape) in group.iter().zip(escape_lanes(z, c, max_iters)) {
+                    out[i] = (escape, 0);
+                }
+            }
+            for &(i, _, _) in groups.remainder() {
+                let (x, y) = pixels[i];
+                out[i] = eval(x as f64 + 0.5, y as f64 + 0.5);
+            }
+        };
+
+        // The new cells and colors are assembled aside and only replace the
+        // grid's once supersampling completed, so a cancelled update leaves
+        // the grid as it was.
+        let computed = self.computed_for(max_iters);
+        let mut planes = Planes::new(self.cells.len(), &computed);
+        let (mut cells, mut rgba) = match shift {
+            Some(shift) => {
+                let source = |idx| self.shifted_index(idx, shift);
+                let mut cells = vec![Cell::default(); self.cells.len()];
+                let mut rgba = vec![0; self.rgba.len()];
+                for (idx, pix) in rgba.chunks_exact_mut(4).enumerate() {
+                    if let Some(src) = source(idx) {
+                        cells[idx] = self.cells[src];
+                        planes.copy(idx, &self.planes, src);
+                        pix.copy_from_slice(&self.rgba[4 * src..4 * src + 4]);
+                    }
+                }
+                (cells, rgba)
+            }
+            None => (
+                vec![Cell::default(); self.cells.len()],
+                vec![0; self.rgba.len()],
+            ),
+        };
+        // Indices of the pixels to compute.
+        let todo: Vec<usize> = match shift {
+            Some(shift) => (0..self.cells.len())
+                .filter(|&idx| self.shifted_index(idx, shift).is_none())
+                .collect(),
+            None => (0..self.cells.len()).collect(),
+        };
+        let mut rebased_pixels = 0;
+        let mut known_interior_pixels = 0;
+        let mut periodic_pixels = 0;
+        let mut filled_pixels = 0;
+        let reused_pixels = cells.len() - todo.len();
+        let mut store = |idx: usize, escape: &Escape, rebases: usize, filled: bool| {
+            cells[idx] = (*escape).into();
+            planes.set(idx, escape);
+            if filled {
+                filled_pixels += 1;
+                return;
+            }
+            rebased_pixels += (rebases > 0) as usize;
+            match escape.shortcut {
+                Some(Shortcut::KnownInterior) => known_interior_pixels += 1,
+                Some(Shortcut::Period(_)) => periodic_pixels += 1,
+                None => {}
+            }
+        };
+        // Julia sets only surround the origin when they are connected, which
+        // they are if its orbit stays bounded.
+        let connected = julia.is_none_or(|c| {
+            let zero = Complex::new(0.0, 0.0);
+            iterate(formula, zero, c, max_iters, Statistics::default()).steps >= max_iters
+        });
+        match shift {
+            None if computed.filled && connected => {
+                // Cells without statistics, so the whole grid is held at once.
+                let eval_cells =
+                    |pixels: &[(usize, usize)], out: &mut [(Cell, Option<Shortcut>, usize)]| {
+                        let mut escapes = [(Escape::default(), 0); BATCH];
+                        eval_batch(pixels, &mut escapes[..pixels.len()]);
+                        for (res, (escape, rebases)) in out.iter_mut().zip(escapes) {
+                            *res = (escape.into(), escape.shortcut, rebases);
+                        }
+                    };
+                // Interior borders don't bound the inside: exterior filaments
+                // thinner than a pixel pass between their pixels.
+                let origin = (
+                    (0.5 - cx / viewport.extent_x) * width as f64,
+                    (0.5 - cy / viewport.extent_y) * height as f64,
+                );
+                let (res, filled) =
+                    subdivide(self.width, self.height, origin, eval_cells, |a, b| {
+                        a.0.steps < max_iters && a.0.steps == b.0.steps
+                    });
+                for (idx, ((cell, shortcut, rebases), filled)) in
+                    res.into_iter().zip(filled).enumerate()
+                {
+                    let escape = Escape {
+                        steps: cell.steps,
+                        smooth: cell.smooth,
+                        shortcut,
+                        ..Escape::default()
+                    };
+                    store(idx, &escape, rebases, filled);
+                }
+            }
+            // Escapes carry every statistic, so only a tile of them is held
+            // at once.
+            _ => {
+                let mut res = vec![(Escape::default(), 0); todo.len().min(TILE)];
+                for tile in todo.chunks(TILE) {
+                    if cancel.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    eval_indices_into(self.width, tile, &eval_batch, &mut res);
+                    for (&idx, (escape, rebases)) in tile.iter().zip(&res) {
+                        store(idx, escape, *rebases, false);
+                    }
+                }
+            }
+        }
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let coloring = self.coloring();
+        let retaken = self.take_histogram(&cells, max_iters);
+        let histogram = match (coloring.mode, retaken.as_ref().or(self.histogram.as_ref())) {
+            (ColorMode::Histogram, Some((_, histogram))) => Some(histogram),
+            _ => None,
+        };
+        let pixel_size = viewport.extent_x / width as f64;
+        let color = |escape| sample_color(escape, &coloring, histogram, max_iters, pixel_size);
+        // Reused cells keep their color unless the coloring changed.
+        let recolor = retaken.is_some() || self.colored.as_ref() != Some(&coloring);
+        let colored =
+            |idx| recolor || shift.is_none_or(|s| shifted_index(width, height, idx, s).is_none());
+        rgba.par_chunks_exact_mut(4)
+            .zip(cells.par_iter())
+            .enumerate()
+            .filter(|&(idx, _)| colored(idx))
+            .for_each(|(idx, (pix, &cell))| pix.copy_from_slice(&color(planes.escape(idx, cell))));
+
+        let mut supersampled_pixels = 0;
+        if let Some(supersampling) = coloring.supersampling {
+            let neighbours = |idx| {
+                let offsets = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)));
+                offsets.filter_map(move |shift| shifted_index(width, height, idx, shift))
+            };
+            // Whether a pixel is supersampled depends on its neighbours, so
+            // the reused pixels bordering the new ones are sampled again.
+            let adaptive = supersampling.threshold.is_some();
+            let candidates = (0..cells.len())
+                .into_par_iter()
+                .filter(|&idx| colored(idx) || adaptive && neighbours(idx).any(colored));
+            let selected: Vec<usize> = if adaptive {
+                let centers: Vec<[u8; 4]> = (cells.par_iter().enumerate())
+                    .map(|(idx, &cell)| color(planes.escape(idx, cell)))
+                    .collect();
+                candidates
+                    .filter(|&idx| {
+                        neighbours(idx).any(|n| supersampling.differs(centers[idx], centers[n]))
+                    })
+                    .collect()
+            } else {
+                candidates.collect()
+            };
+            let samples: Vec<[u8; 4]> = selected
+                .par_iter()
+                .map(|&idx| {
+                    let (x, y) = (idx % width, idx / width);
+                    let samples = supersampling.offsets(x, y).map(|(u, v)| {
+                        let (escape, _) = eval(x as f64 + u, y as f64 + v);
+                        color(escape)
+                    });
+                    supersampling::average(samples)
+                })
+                .collect();
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            for (&idx, sample) in selected.iter().zip(&samples) {
+                rgba[4 * idx..4 * idx + 4].copy_from_slice(sample);
+            }
+            supersampled_pixels = selected.len();
+        }
+        self.cells = cells;
+        self.planes = planes;
+        self.rgba = rgba;
+        if retaken.is_some() {
+            self.histogram = retaken;
+        }
+        self.colored = Some(coloring);
+        self.computed = Some(computed);
+        Some(UpdateStats {
+            elapsed: start_time.elapsed(),
+            max_iters,
+            precision,
+            reference_len: reference.as_ref().map_or(0, ReferenceOrbit::len),
+            rebased_pixels,
+            known_interior_pixels,
+            periodic_pixels,
+            filled_pixels,
+            reused_pixels,
+            supersampled_pixels,
+        })
+    }
+
+    /// Copies the cell colors into `screen`, an RGBA buffer of the grid's size.
+    pub fn draw(&self, screen: &mut [u8]) {
+        screen.copy_from_slice(&self.rgba);
+    }
+}
+
+/// Color of the pixel or sample `escape` with `coloring`, given the
+/// distribution of the view in histogram mode.
+fn sample_color(
+    escape: Escape,
+    coloring: &Coloring,
+    histogram: Option<&Histogram>,
+    max_iters: usize,
+    pixel_size: f64,
+) -> [u8; 4] {
+    let (mode, palette) = (coloring.mode, &coloring.palette);
+    let interior = escape.steps >= max_iters;
+    let rgba = match (histogram, interior) {
+        (Some(histogram), false) => palette.equalized(histogram.fraction(escape.smooth)),
+        // Trap coloring covers the interior itself.
+        (_, true) if coloring.interior.iterates() && !mode.uses_trap() => {
+            let palette = &coloring.interior_palette;
+            interior_to_rgb(escape, coloring.interior, palette, pixel_size)
+        }
+        _ => escape_to_rgb(escape, max_iters, mode, palette, pixel_size),
+    };
+    match (coloring.lighting, escape.normal) {
+        (Some(lighting), Some(normal)) => lighting.shade(rgba, normal),
+        _ => rgba,
+    }
+}
+
+/// Evaluates the pixels at `indices` of a grid `width` pixels wide, handing
+/// them to `eval` in parallel batches of up to [`BATCH`] pixels.
+pub(crate) fn eval_indices<T, E>(width: usize, indices: &[usize], eval: &E) -> Vec<T>
+where
+    T: Copy + Default + Send,
+    E: Fn(&[(usize, usize)], &mut [T]) + Sync,
+{
+    let mut res = vec![T::default(); indices.len()];
+    eval_indices_into(width, indices, eval, &mut res);
+    res
+}
+
+/// Like [`eval_indices`], but writes the values into the start of `res`.
+fn eval_indices_into<T, E>(width: usize, indices: &[usize], eval: &E, res: &mut [T])
+where
+    T: Send,
+    E: Fn(&[(usize, usize)], &mut [T]) + Sync,
+{
+    indices
+        .par_chunks(BATCH)
+        .zip(res.par_chunks_mut(BATCH))
+        .for_each(|(chunk, out)| {
+            let mut pixels = [(0, 0); BATCH];
+            for (pixel, &idx) in pixels.iter_mut().zip(chunk) {
+                *pixel = (idx % width, idx / width);
+            }
+            eval(&pixels[..chunk.len()], &mut out[..chunk.len()]);
+        });
+}
+
+/// Index of the cell `shift` pixels away from cell `idx` of a
+/// `width`x`height` grid, if it lies on the grid.
+fn shifted_index(width: usize, height: usize, idx: usize, shift: (isize, isize)) -> Option<usize> {
+    let x = (idx % width) as isize + shift.0;
+    let y = (idx / width) as isize + shift.1;
+    let on_grid = (0..width as isize).contains(&x) && (0..height as isize).contains(&y);
+    on_grid.then(|| x as usize + y as usize * width)
+}
@@ -0,0 +1,641 @@
+use std::f64::consts::PI;
+
+use num::complex::Complex;
+
+use crate::bignum::BigComplex;
+use crate::formula::{Formula, Mandelbrot};
+use crate::trap::OrbitTrap;
+
+/// Default iteration limit for a single point.
+pub const MAX_ITERS: usize = 500;
+
+/// Escape radius used for the smooth count. Iterating well past radius 2
+/// keeps the log-log renormalization continuous across iteration bands.
+const SMOOTH_BAILOUT: f64 = 256.0;
+/// Upper bound on the iterations spent reaching `SMOOTH_BAILOUT`, for
+/// formulas whose orbits grow slowly after escaping.
+const MAX_SMOOTH_ITERS: usize = 64;
+/// Squared distance below which an orbit is considered to have returned to
+/// an earlier value, i.e. to have settled on an attracting cycle.
+pub(crate) const PERIODICITY_EPSILON: f64 = 1e-24;
+/// Squared distance within which a divisor of a detected period already
+/// returns to the converged point. Orbits approaching a cycle from
+/// alternating sides are detected at a multiple of its period, and their
+/// distance to the cycle is far larger than `PERIODICITY_EPSILON`.
+const CYCLE_EPSILON: f64 = 1e-12;
+/// Magnitude at which a tracked derivative is scaled down by
+/// `2^DERIVATIVE_RESCALE_BITS`. Near the boundary it roughly doubles every
+/// iteration and would overflow within about a thousand of them.
+const DERIVATIVE_RESCALE_AT: f64 = 1e150;
+const DERIVATIVE_RESCALE_BITS: i32 = 400;
+
+/// Result of iterating a single point.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Escape {
+    /// Iteration at which [`Formula::escaped`] first held, `max_iters` for
+    /// interior points.
+    pub steps: usize,
+    /// Continuous escape count on the same scale as `steps`, clamped to
+    /// `0..=max_iters`.
+    pub smooth: f64,
+    /// Last iterate: the first one outside the escape radius for escaped
+    /// points, the one the iteration stopped at for interior points.
+    pub z: Complex<f64>,
+    /// Estimated distance from the point to the boundary of the set, in
+    /// units of the plane. Only set for escaped points whose derivative was
+    /// tracked, see [`escape_distance`].
+    pub distance: Option<f64>,
+    /// Unit vector along `z / dz`, the direction in which the potential of
+    /// the set grows, for shading the image as a lit surface. Set together
+    /// with `distance`.
+    pub normal: Option<Complex<f64>>,
+    /// Closest approach of the orbit to the trap it was iterated with, see
+    /// [`escape_statistics`]. Recorded for interior points as well.
+    pub trap: Option<f64>,
+    /// Orbit average it was iterated with, in `0..=1`, see [`Average`].
+    /// Only set for escaped points.
+    pub average: Option<f64>,
+    /// Attracting cycle of an interior point, see [`Statistics::cycle`].
+    pub cycle: Option<Cycle>,
+    /// Set when an interior point was recognized without iterating up to
+    /// `max_iters`.
+    pub shortcut: Option<Shortcut>,
+}
+
+/// Way an interior point was detected early.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shortcut {
+    /// The parameter lies in a region known to be interior, see
+    /// [`Formula::known_interior`].
+    KnownInterior,
+    /// The orbit returned to an earlier value after this many iterations.
+    Period(usize),
+}
+
+/// Derivative tracked along an orbit for the distance estimate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Derivative {
+    /// With respect to the parameter `c`, for parameter-plane images.
+    Parameter,
+    /// With respect to the starting point, for Julia sets.
+    Initial,
+}
+
+/// Attracting cycle an interior orbit settled on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cycle {
+    /// Steps after which the orbit returned, which may be a multiple of the
+    /// true period if it was missed at first.
+    pub period: usize,
+    /// Derivative of the cycle's return map, inside the unit disc for
+    /// attracting cycles. It is 0 at the nucleus of a hyperbolic component
+    /// and its angle runs once around towards every bulb on its boundary.
+    /// Needs [`Formula::derivative`].
+    pub multiplier: Option<Complex<f64>>,
+    /// Estimated distance from the parameter to the boundary of its
+    /// hyperbolic component, in units of the plane. Only for parameter-plane
+    /// images of formulas with [`Formula::second_derivative`].
+    pub distance: Option<f64>,
+}
+
+/// Statistics recorded along an orbit besides its escape count.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Statistics {
+    /// Derivative tracked for the distance estimate and surface normal.
+    pub derivative: Option<Derivative>,
+    /// Trap whose closest approach is recorded.
+    pub trap: Option<OrbitTrap>,
+    /// Quantity averaged over the orbit.
+    pub average: Option<Average>,
+    /// Find the attracting cycle of interior points, with the derivative
+    /// with respect to the parameter or the starting point. Only orbits
+    /// that were caught by cycle detection get one, so arbitrary-precision
+    /// and perturbation rendering get none. This also iterates parameters
+    /// [`Formula::known_interior`] would skip.
+    pub cycle: Option<Derivative>,
+}
+
+/// Quantity averaged over the iterates of an escaping orbit for the
+/// averaging colorings, each in `0..=1`.
+///
+/// Iterating continues to a large bailout for the average, and the averages
+/// with and without the last iterate are blended by the smooth iteration
+/// fraction, so the result is continuous across iteration bands. The first
+/// `skip` iterates are left out, as they vary little between neighbouring
+/// pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Average {
+    /// `(1 + sin(density arg z)) / 2`, which draws stripes radiating from
+    /// the set.
+    Stripe { density: f64, skip: usize },
+    /// How sharply the orbit turns at every iterate, its turning angle
+    /// divided by pi.
+    Curvature { skip: usize },
+    /// Where `|z|` lies between the bounds `| |z'|^d - |c| |` and
+    /// `|z'|^d + |c|` the triangle inequality gives for the step from the
+    /// previous iterate `z'`.
+    TriangleInequality { skip: usize },
+}
+
+impl Average {
+    pub fn skip(&self) -> usize {
+        match *self {
+            Average::Stripe { skip, .. }
+            | Average::Curvature { skip }
+            | Average::TriangleInequality { skip } => skip,
+        }
+    }
+}
+
+/// Statistics of an orbit while it is being iterated.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Recorder {
+    tracked: Option<Tracked>,
+    /// Trap and the closest approach to it so far.
+    trap: Option<(OrbitTrap, f64)>,
+    averager: Option<Averager>,
+}
+
+impl Recorder {
+    /// Starts recording `statistics` of an orbit with the parameter `c`.
+    pub(crate) fn new(statistics: Statistics, c: Complex<f64>) -> Self {
+        Self {
+            tracked: statistics.derivative.map(Tracked::new),
+            trap: statistics.trap.map(|trap| (trap, f64::INFINITY)),
+            averager: statistics.average.map(|average| Averager::new(average, c)),
+        }
+    }
+
+    /// Advances the derivative of the orbit at `z` by one step, dropping it
+    /// if `formula` has none.
+    pub(crate) fn step<F: Formula + ?Sized>(&mut self, formula: &F, z: Complex<f64>) {
+        self.tracked = self.tracked.and_then(|tracked| tracked.step(formula, z));
+        if let Some(averager) = &mut self.averager {
+            averager.step(formula, z);
+        }
+    }
+
+    /// Records `z`, an iterate after the starting point.
+    pub(crate) fn visit(&mut self, z: Complex<f64>) {
+        if let Some((trap, closest)) = &mut self.trap {
+            *closest = closest.min(trap.distance(z));
+        }
+        self.accumulate(z);
+    }
+
+    /// Records `z`, an iterate after the orbit escaped, in the statistics
+    /// that carry on to the large bailout.
+    fn accumulate(&mut self, z: Complex<f64>) {
+        if let Some(averager) = &mut self.averager {
+            averager.accumulate(z);
+        }
+    }
+
+    /// Adds the statistics that don't depend on how the orbit ended to
+    /// `escape`.
+    pub(crate) fn finish(&self, escape: Escape) -> Escape {
+        Escape {
+            trap: self.trap.and_then(|(trap, closest)| trap.trapped(closest)),
+            ..escape
+        }
+    }
+}
+
+/// Running [`Average`] of an orbit.
+#[derive(Clone, Copy, Debug)]
+struct Averager {
+    average: Average,
+    c_norm: f64,
+    /// Steps taken so far.
+    steps: usize,
+    /// The last two iterates stepped from, latest first.
+    previous: [Complex<f64>; 2],
+    /// `|previous[0]|` to the degree of the formula.
+    power: f64,
+    sum: f64,
+    count: usize,
+    /// Term added to `sum` last.
+    last: f64,
+}
+
+impl Averager {
+    fn new(average: Average, c: Complex<f64>) -> Self {
+        Self {
+            average,
+            c_norm: c.norm(),
+            steps: 0,
+            previous: [Complex::new(0.0, 0.0); 2],
+            power: 0.0,
+            sum: 0.0,
+            count: 0,
+            last: 0.0,
+        }
+    }
+
+    fn step<F: Formula + ?Sized>(&mut self, formula: &F, z: Complex<f64>) {
+        self.steps += 1;
+        self.previous = [z, self.previous[0]];
+        if let Average::TriangleInequality { .. } = self.average {
+            self.power = z.norm().powf(formula.degree());
+        }
+    }
+
+    /// Adds the term of `z`, the iterate reached by the last step.
+    fn accumulate(&mut self, z: Complex<f64>) {
+        if self.steps <= self.average.skip() {
+            return;
+        }
+        let term = match self.average {
+            Average::Stripe { density, .. } => 0.5 + 0.5 * (density * z.arg()).sin(),
+            Average::Curvature { .. } if self.steps >= 2 => {
+                let [a, b] = self.previous;
+                ((z - a) / (a - b)).arg().abs() / PI
+            }
+            Average::Curvature { .. } => return,
+            Average::TriangleInequality { .. } => {
+                let low = (self.power - self.c_norm).abs();
+                let high = self.power + self.c_norm;
+                (z.norm() - low) / (high - low)
+            }
+        };
+        // Degenerate steps, e.g. from the origin, have no term.
+        if term.is_finite() {
+            self.sum += term;
+            self.count += 1;
+            self.last = term;
+        }
+    }
+
+    /// Average of an orbit that ended at `z`, past the large bailout.
+    fn value(&self, z: Complex<f64>, degree: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let with_last = self.sum / self.count as f64;
+        let without_last = match self.count {
+            1 => with_last,
+            count => (self.sum - self.last) / (count - 1) as f64,
+        };
+        // 1 where `z` just passed the bailout, falling to 0 where the orbit
+        // would have passed it one step earlier.
+        let weight = 1.0 - (z.norm().ln() / SMOOTH_BAILOUT.ln()).ln() / degree.ln();
+        let weight = weight.clamp(0.0, 1.0);
+        Some(without_last + (with_last - without_last) * weight)
+    }
+}
+
+/// Derivative of an orbit while it is being tracked, stored as
+/// `dz * 2^exponent` so long orbits don't overflow.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Tracked {
+    derivative: Derivative,
+    dz: Complex<f64>,
+    exponent: i32,
+}
+
+impl Tracked {
+    pub(crate) fn new(derivative: Derivative) -> Self {
+        let dz = match derivative {
+            Derivative::Parameter => Complex::new(0.0, 0.0),
+            Derivative::Initial => Complex::new(1.0, 0.0),
+        };
+        Self {
+            derivative,
+            dz,
+            exponent: 0,
+        }
+    }
+
+    /// Advances the derivative of the orbit at `z` by one step of
+    /// `formula`, or returns `None` if the formula has no derivative.
+    fn step<F: Formula + ?Sized>(mut self, formula: &F, z: Complex<f64>) -> Option<Self> {
+        self.dz = formula.derivative(z)? * self.dz;
+        if self.derivative == Derivative::Parameter {
+            self.dz += 2f64.powi(-self.exponent);
+        }
+        if self.dz.norm_sqr() > DERIVATIVE_RESCALE_AT * DERIVATIVE_RESCALE_AT {
+            self.dz *= 2f64.powi(-DERIVATIVE_RESCALE_BITS);
+            self.exponent += DERIVATIVE_RESCALE_BITS;
+        }
+        Some(self)
+    }
+
+    /// Distance estimate of an orbit that reached `z`.
+    fn distance(&self, z: Complex<f64>) -> f64 {
+        z.norm() * z.norm().ln() / self.dz.norm() * 2f64.powi(-self.exponent)
+    }
+
+    /// Surface normal of an orbit that reached `z`. The exponent only
+    /// scales `dz`, so it drops out.
+    fn normal(&self, z: Complex<f64>) -> Option<Complex<f64>> {
+        let u = z / self.dz;
+        let norm = u.norm();
+        (norm.is_finite() && norm > 0.0).then(|| u / norm)
+    }
+}
+
+/// Returns the number of iterations it takes the orbit of `x + yi` to leave
+/// the radius-2 disc, or `max_iters` if it never does.
+pub fn get_mondelbrot(x: f64, y: f64, max_iters: usize) -> usize {
+    escape(x, y, max_iters).steps
+}
+
+/// Iterates `x + yi`, returning both the banded and the smooth escape count.
+pub fn escape(x: f64, y: f64, max_iters: usize) -> Escape {
+    escape_parameter(&Mandelbrot, Complex::new(x, y), max_iters)
+}
+
+/// Iterates the point `x + yi` of the Julia set for the parameter `c`.
+pub fn escape_julia(x: f64, y: f64, c: (f64, f64), max_iters: usize) -> Escape {
+    escape_with(
+        &Mandelbrot,
+        Complex::new(x, y),
+        Complex::new(c.0, c.1),
+        max_iters,
+    )
+}
+
+/// Iterates the parameter `c` of `formula` from its initial value, skipping
+/// parameters the formula already knows to be interior.
+pub fn escape_parameter<F: Formula + ?Sized>(
+    formula: &F,
+    c: Complex<f64>,
+    max_iters: usize,
+) -> Escape {
+    iterate_parameter(formula, c, max_iters, Statistics::default())
+}
+
+/// [`escape_parameter`] that also records `statistics`.
+pub(crate) fn iterate_parameter<F: Formula + ?Sized>(
+    formula: &F,
+    c: Complex<f64>,
+    max_iters: usize,
+    statistics: Statistics,
+) -> Escape {
+    // Traps and cycles are recorded inside the set as well, which takes the
+    // orbit.
+    let inside = statistics.trap.is_some() || statistics.cycle.is_some();
+    if !inside && formula.known_interior(c) {
+        return Escape {
+            shortcut: Some(Shortcut::KnownInterior),
+            ..interior(formula.initial_z(c), max_iters)
+        };
+    }
+    iterate(formula, formula.initial_z(c), c, max_iters, statistics)
+}
+
+/// Iterates `formula` from `z` with the parameter `c`.
+///
+/// Pass `formula.initial_z(c)` as `z` for parameter-plane (Mandelbrot-like)
+/// images and the pixel as `z` with a fixed `c` for Julia sets.
+///
+/// Orbits caught in an attracting cycle are stopped early with Brent's
+/// cycle detection: `z` is saved at power-of-two intervals and compared
+/// against every later value until the next save.
+pub fn escape_with<F: Formula + ?Sized>(
+    formula: &F,
+    z: Complex<f64>,
+    c: Complex<f64>,
+    max_iters: usize,
+) -> Escape {
+    iterate(formula, z, c, max_iters, Statistics::default())
+}
+
+/// [`escape_with`] that also tracks the `derivative` of the orbit to
+/// estimate the distance of escaped points to the set and their surface
+/// normal, see [`Escape::distance`] and [`Escape::normal`].
+///
+/// The estimate `|z| ln|z| / |dz|` is within a factor of four of the true
+/// distance, which is plenty to draw boundaries and filaments that are
+/// thinner than a pixel. Formulas without [`Formula::derivative`] get no
+/// estimate.
+pub fn escape_distance<F: Formula + ?Sized>(
+    formula: &F,
+    z: Complex<f64>,
+    c: Complex<f64>,
+    max_iters: usize,
+    derivative: Derivative,
+) -> Escape {
+    let statistics = Statistics {
+        derivative: Some(derivative),
+        ..Statistics::default()
+    };
+    iterate(formula, z, c, max_iters, statistics)
+}
+
+/// [`escape_with`] that also records `statistics` of the orbit.
+///
+/// Orbits caught in an attracting cycle still stop early, which leaves the
+/// trap statistics unchanged as the rest of the orbit repeats the cycle.
+pub fn escape_statistics<F: Formula + ?Sized>(
+    formula: &F,
+    z: Complex<f64>,
+    c: Complex<f64>,
+    max_iters: usize,
+    statistics: Statistics,
+) -> Escape {
+    iterate(formula, z, c, max_iters, statistics)
+}
+
+pub(crate) fn iterate<F: Formula + ?Sized>(
+    formula: &F,
+    mut z: Complex<f64>,
+    c: Complex<f64>,
+    max_iters: usize,
+    statistics: Statistics,
+) -> Escape {
+    let mut recorder = Recorder::new(statistics, c);
+    let mut saved = z;
+    let mut window = 1;
+    let mut since_saved = 0;
+    let mut last_return = None;
+    for i in 0..=max_iters {
+        if formula.escaped(z) {
+            return smooth_escape(formula, z, c, i, max_iters, recorder);
+        }
+        recorder.step(formula, z);
+        z = formula.step(z, c);
+        recorder.visit(z);
+        since_saved += 1;
+        let distance = (z - saved).norm_sqr();
+        if distance < PERIODICITY_EPSILON {
+            // Orbits also linger near repelling cycles, e.g. at Misiurewicz
+            // points on the boundary, so only stop once a second return
+            // shows the cycle pulling the orbit closer.
+            if last_return.is_some_and(|last| distance <= last) {
+                let cycle = statistics
+                    .cycle
+                    .map(|derivative| attracting_cycle(formula, z, c, since_saved, derivative));
+                return recorder.finish(Escape {
+                    cycle,
+                    shortcut: Some(Shortcut::Period(since_saved)),
+                    ..interior(z, max_iters)
+                });
+            }
+            last_return = Some(distance);
+            saved = z;
+            since_saved = 0;
+            continue;
+        }
+        if since_saved == window {
+            saved = z;
+            window *= 2;
+            since_saved = 0;
+        }
+    }
+    recorder.finish(interior(z, max_iters))
+}
+
+/// High precision version of [`escape_with`] for deep zooms.
+///
+/// Returns `None` if `formula` has no [`Formula::step_big`].
+pub fn escape_big<F: Formula + ?Sized>(
+    formula: &F,
+    z: BigComplex,
+    c: &BigComplex,
+    max_iters: usize,
+) -> Option<Escape> {
+    iterate_big(formula, z, c, max_iters, Statistics::default())
+}
+
+/// [`escape_big`] that also records `statistics`, in `f64`.
+pub(crate) fn iterate_big<F: Formula + ?Sized>(
+    formula: &F,
+    mut z: BigComplex,
+    c: &BigComplex,
+    max_iters: usize,
+    statistics: Statistics,
+) -> Option<Escape> {
+    let mut recorder = Recorder::new(statistics, c.to_f64());
+    for i in 0..=max_iters {
+        // Escape only depends on the magnitude, which f64 resolves fine.
+        let zf = z.to_f64();
+        if i > 0 {
+            recorder.visit(zf);
+        }
+        if formula.escaped(zf) {
+            let escape = smooth_escape(formula, zf, c.to_f64(), i, max_iters, recorder);
+            return Some(escape);
+        }
+        recorder.step(formula, zf);
+        z = formula.step_big(&z, c)?;
+    }
+    Some(recorder.finish(interior(z.to_f64(), max_iters)))
+}
+
+/// Whether `formula` implements [`Formula::step_big`].
+pub(crate) fn supports_big<F: Formula + ?Sized>(formula: &F) -> bool {
+    let zero = BigComplex::from_f64(Complex::new(0.0, 0.0), 0);
+    formula.step_big(&zero, &zero).is_some()
+}
+
+/// Whether `formula` implements [`Formula::step_delta`].
+pub(crate) fn supports_perturbation<F: Formula + ?Sized>(formula: &F) -> bool {
+    let zero = Complex::new(0.0, 0.0);
+    formula.step_delta(zero, zero, zero).is_some()
+}
+
+/// Result of a point that did not escape, with `z` as its last iterate.
+pub(crate) fn interior(z: Complex<f64>, max_iters: usize) -> Escape {
+    Escape {
+        steps: max_iters,
+        smooth: max_iters as f64,
+        z,
+        distance: None,
+        normal: None,
+        trap: None,
+        average: None,
+        cycle: None,
+        shortcut: None,
+    }
+}
+
+/// Cycle through `z`, a point an orbit converged to after returning to it
+/// every `period` steps.
+fn attracting_cycle<F: Formula + ?Sized>(
+    formula: &F,
+    mut z: Complex<f64>,
+    c: Complex<f64>,
+    period: usize,
+    derivative: Derivative,
+) -> Cycle {
+    let period = (1..period)
+        .filter(|&p| period.is_multiple_of(p))
+        .find(|&p| {
+            let returned = (0..p).fold(z, |w, _| formula.step(w, c));
+            (returned - z).norm_sqr() < CYCLE_EPSILON
+        })
+        .unwrap_or(period);
+    let (zero, one) = (Complex::new(0.0, 0.0), Complex::new(1.0, 0.0));
+    // Derivatives of the return map by `z` and `c`, and its second
+    // derivatives by `z` and by `z` and `c`.
+    let (mut dz, mut dc, mut dzdz, mut dcdz) = (one, zero, zero, zero);
+    let mut second = true;
+    for _ in 0..period {
+        let Some(d1) = formula.derivative(z) else {
+            return Cycle {
+                period,
+                multiplier: None,
+                distance: None,
+            };
+        };
+        match formula.second_derivative(z) {
+            Some(d2) => {
+                dcdz = d2 * dc * dz + d1 * dcdz;
+                dzdz = d2 * dz * dz + d1 * dzdz;
+            }
+            None => second = false,
+        }
+        dc = d1 * dc + one;
+        dz = d1 * dz;
+        z = formula.step(z, c);
+    }
+    let distance = (second && derivative == Derivative::Parameter)
+        .then(|| (1.0 - dz.norm_sqr()) / (dcdz + dzdz * dc / (one - dz)).norm())
+        .filter(|distance| distance.is_finite() && *distance > 0.0);
+    Cycle {
+        period,
+        multiplier: Some(dz),
+        distance,
+    }
+}
+
+/// Continues an orbit that escaped after `steps` iterations to compute its
+/// smooth escape count, and finishes the statistics of `recorder`.
+pub(crate) fn smooth_escape<F: Formula + ?Sized>(
+    formula: &F,
+    escaped: Complex<f64>,
+    c: Complex<f64>,
+    steps: usize,
+    max_iters: usize,
+    mut recorder: Recorder,
+) -> Escape {
+    let mut z = escaped;
+    // Once the orbit has escaped it diverges, so reaching the large bailout
+    // only takes a handful of extra iterations. The distance estimate is
+    // more accurate out there as well.
+    let mut n = steps;
+    while z.norm_sqr() < SMOOTH_BAILOUT * SMOOTH_BAILOUT && n < steps + MAX_SMOOTH_ITERS {
+        recorder.step(formula, z);
+        z = formula.step(z, c);
+        recorder.accumulate(z);
+        n += 1;
+    }
+    let smooth = n as f64 + 1.0 - z.norm().log2().ln() / formula.degree().ln();
+    let distance = recorder.tracked.map(|tracked| tracked.distance(z));
+    let normal = recorder.tracked.and_then(|tracked| tracked.normal(z));
+    let average = recorder
+        .averager
+        .and_then(|averager| averager.value(z, formula.degree()));
+    recorder.finish(Escape {
+        steps,
+        smooth: smooth.clamp(0.0, max_iters as f64),
+        z: escaped,
+        distance,
+        normal,
+        trap: None,
+        average,
+        cycle: None,
+        shortcut: None,
+    })
+}
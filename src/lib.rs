@@ -0,0 +1,61 @@
+//! Escape-time renderer for the Mandelbrot set.
+//!
+//! The library is windowless: it fills caller-provided RGBA buffers and can be
+//! used from headless tools. The interactive viewer lives in the
+//! `mandelbrot-rs` binary behind the `viewer` feature.
+
+#![deny(clippy::all)]
+#![forbid(unsafe_code)]
+
+mod bignum;
+mod color;
+mod formula;
+mod grid;
+mod kernel;
+mod lighting;
+mod palette;
+mod palette_file;
+mod perturbation;
+mod simd;
+mod subdivide;
+mod supersampling;
+mod trap;
+mod viewport;
+
+pub use bignum::{BigComplex, BigFixed, MAX_DECIMAL_DIGITS};
+pub use color::{escape_to_rgb, hsl_to_rgba, interior_to_rgb, ColorMode, InteriorMode};
+pub use formula::{BurningShip, Celtic, Formula, Fractal, Mandelbrot, Multibrot, Tricorn};
+pub use grid::{Cell, MandelbrotGrid, Precision, Strategy, UpdateStats};
+pub use kernel::{
+    escape, escape_big, escape_distance, escape_julia, escape_parameter, escape_statistics,
+    escape_with, get_mondelbrot, Average, Cycle, Derivative, Escape, Shortcut, Statistics,
+    MAX_ITERS,
+};
+pub use lighting::{Lighting, Shading};
+pub use num::complex::Complex;
+pub use palette::{Interpolation, Palette, Repeat, Stop};
+pub use palette_file::PaletteError;
+pub use perturbation::ReferenceOrbit;
+pub use simd::{escape_lanes, simd_compiled, LANES};
+pub use supersampling::{Pattern, Supersampling};
+pub use trap::OrbitTrap;
+pub use viewport::Viewport;
+
+/// Renders `viewport` at `width`x`height` into `buffer` as tightly packed RGBA.
+///
+/// Panics if `buffer` is not exactly `4 * width * height` bytes long.
+pub fn render(
+    viewport: Viewport,
+    width: usize,
+    height: usize,
+    max_iters: usize,
+    buffer: &mut [u8],
+) -> UpdateStats {
+    assert_eq!(buffer.len(), 4 * width * height, "buffer size mismatch");
+    let mut grid = MandelbrotGrid::new(width, height);
+    grid.viewport = viewport;
+    grid.max_iters = max_iters;
+    let stats = grid.update();
+    grid.draw(buffer);
+    stats
+}
@@ -0,0 +1,101 @@
+use num::complex::Complex;
+
+use crate::color::{linear_to_srgb, srgb_to_linear};
+
+/// Exponent of the specular highlight; higher is a smaller, sharper spot.
+const SHININESS: f64 = 20.0;
+/// Brightness of the specular highlight at its center.
+const SPECULAR: f64 = 0.5;
+
+/// Reflection model of a [`Lighting`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Shading {
+    /// Diffuse light only, a matte surface.
+    Lambert,
+    /// Diffuse light plus a highlight where the surface reflects the light
+    /// towards the viewer.
+    #[default]
+    BlinnPhong,
+}
+
+/// Light shining onto the image as if the potential of the set were a
+/// relief, which makes the set look embossed.
+///
+/// Every escaped pixel is shaded by its surface normal, see
+/// [`crate::Escape::normal`], and the shade is blended with its palette
+/// color in linear light.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lighting {
+    pub shading: Shading,
+    /// Direction the light comes from, in degrees counterclockwise from the
+    /// right as seen in the image.
+    pub angle: f64,
+    /// Height of the light above the image plane per unit of horizontal
+    /// distance; low lights cast long shades, high ones flatten the relief.
+    pub height: f64,
+    /// Blend between the plain palette color at 0 and the fully lit one at
+    /// 1.
+    pub intensity: f64,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            shading: Shading::default(),
+            angle: 45.0,
+            height: 1.5,
+            intensity: 1.0,
+        }
+    }
+}
+
+impl Lighting {
+    /// Lights the palette color `rgba` of a pixel whose surface normal
+    /// points along `normal`, a unit vector in the plane.
+    pub fn shade(&self, rgba: [u8; 4], normal: Complex<f64>) -> [u8; 4] {
+        // The relief rises at 45 degrees everywhere, only its direction
+        // changes from pixel to pixel.
+        let normal = normalize([normal.re, normal.im, 1.0]);
+        // Rows go down the imaginary axis, so the image is the plane
+        // mirrored vertically.
+        let angle = self.angle.to_radians();
+        let light = normalize([angle.cos(), -angle.sin(), self.height]);
+        let diffuse = dot(normal, light).max(0.0);
+        let specular = match self.shading {
+            Shading::Lambert => 0.0,
+            Shading::BlinnPhong => {
+                let half = normalize([light[0], light[1], light[2] + 1.0]);
+                SPECULAR * dot(normal, half).max(0.0).powf(SHININESS)
+            }
+        };
+        let lit = |c: u8| {
+            let c = srgb_to_linear(c);
+            let shaded = c * diffuse + specular;
+            linear_to_srgb(c + (shaded - c) * self.intensity)
+        };
+        let [r, g, b, a] = rgba;
+        [lit(r), lit(g), lit(b), a]
+    }
+
+    /// The lighting after this one when cycling through them in the viewer:
+    /// unlit, Blinn-Phong and Lambert.
+    pub fn next(lighting: Option<Self>) -> Option<Self> {
+        match lighting {
+            None => Some(Self::default()),
+            Some(lighting) if lighting.shading == Shading::BlinnPhong => Some(Self {
+                shading: Shading::Lambert,
+                ..lighting
+            }),
+            Some(_) => None,
+        }
+    }
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalize(v: [f64; 3]) -> [f64; 3] {
+    let norm = dot(v, v).sqrt();
+    v.map(|c| c / norm)
+}
@@ -0,0 +1,320 @@
+use crate::color::{hsl_to_rgba, linear_to_oklab, linear_to_srgb, oklab_to_linear, srgb_to_linear};
+
+/// Color of a gradient at a position in `0..=1`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stop {
+    pub position: f64,
+    /// sRGB color.
+    pub color: [u8; 3],
+}
+
+impl Stop {
+    pub fn new(position: f64, color: [u8; 3]) -> Self {
+        Self { position, color }
+    }
+}
+
+/// Color space in which neighbouring stops are blended.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Straight on the sRGB values; midpoints come out darker than expected.
+    Rgb,
+    /// On linear light, as physically mixing the two colors would.
+    LinearRgb,
+    /// In OKLab, giving perceptually even steps.
+    #[default]
+    Oklab,
+}
+
+/// How positions outside of `0..=1` are folded back onto the gradient.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Repeat {
+    /// Start over at the first stop.
+    #[default]
+    Wrap,
+    /// Run the gradient backwards every other time.
+    Mirror,
+    /// Keep the color of the first or last stop.
+    Clamp,
+}
+
+/// Gradient of color stops that escape counts are mapped onto.
+///
+/// Escape counts are turned into a number of gradient lengths, which is
+/// multiplied by `scale`, shifted by `offset` and folded back onto the stops
+/// according to `repeat`. Interior points are black, except with the
+/// classic palette, which lightens into a white set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    pub name: String,
+    /// Sorted by position.
+    stops: Vec<Stop>,
+    pub interpolation: Interpolation,
+    /// Shift along the gradient, in gradient lengths.
+    pub offset: f64,
+    /// Speed at which escape counts run through the gradient.
+    pub scale: f64,
+    pub repeat: Repeat,
+    /// Whether [`Palette::color`] sweeps the hue and lightens towards the
+    /// set like the original renderer did, instead of following the stops.
+    classic: bool,
+}
+
+/// Palette shipped with the library.
+struct Builtin {
+    name: &'static str,
+    interpolation: Interpolation,
+    repeat: Repeat,
+    /// Positions and `0xRRGGBB` colors.
+    stops: &'static [(f64, u32)],
+    classic: bool,
+}
+
+const BUILTIN: &[Builtin] = &[
+    Builtin {
+        name: "ultra",
+        interpolation: Interpolation::Oklab,
+        repeat: Repeat::Wrap,
+        stops: &[
+            (0.0, 0x000764),
+            (0.16, 0x206bcb),
+            (0.42, 0xedffff),
+            (0.6425, 0xffaa00),
+            (0.8575, 0x000200),
+            (1.0, 0x000764),
+        ],
+        classic: false,
+    },
+    Builtin {
+        name: "fire",
+        interpolation: Interpolation::Oklab,
+        repeat: Repeat::Mirror,
+        stops: &[
+            (0.0, 0x000000),
+            (0.3, 0xc81e00),
+            (0.7, 0xffc800),
+            (1.0, 0xffffff),
+        ],
+        classic: false,
+    },
+    Builtin {
+        name: "ocean",
+        interpolation: Interpolation::Oklab,
+        repeat: Repeat::Mirror,
+        stops: &[
+            (0.0, 0x00073c),
+            (0.35, 0x005ab4),
+            (0.7, 0x50dce6),
+            (1.0, 0xffffff),
+        ],
+        classic: false,
+    },
+    Builtin {
+        name: "grayscale",
+        interpolation: Interpolation::Oklab,
+        repeat: Repeat::Mirror,
+        stops: &[(0.0, 0x000000), (1.0, 0xffffff)],
+        classic: false,
+    },
+    // Perceptually uniform and readable with every common color vision
+    // deficiency.
+    Builtin {
+        name: "viridis",
+        interpolation: Interpolation::Oklab,
+        repeat: Repeat::Mirror,
+        stops: &[
+            (0.0, 0x440154),
+            (0.25, 0x3b528b),
+            (0.5, 0x21918c),
+            (0.75, 0x5ec962),
+            (1.0, 0xfde725),
+        ],
+        classic: false,
+    },
+    // Designed to look the same with and without red-green deficiencies.
+    Builtin {
+        name: "cividis",
+        interpolation: Interpolation::Oklab,
+        repeat: Repeat::Mirror,
+        stops: &[
+            (0.0, 0x00224e),
+            (0.2, 0x35456c),
+            (0.4, 0x666970),
+            (0.6, 0x948e77),
+            (0.8, 0xc8b866),
+            (1.0, 0xfee838),
+        ],
+        classic: false,
+    },
+    // The escape time coloring of the original renderer, for reproducing
+    // its images. The stops are the hue circle at its saturation and
+    // lightness, which the other colorings sweep through.
+    Builtin {
+        name: "classic",
+        interpolation: Interpolation::Rgb,
+        repeat: Repeat::Wrap,
+        stops: &[
+            (0.0, 0xbf3f3f),
+            (1.0 / 6.0, 0xbfbf3f),
+            (2.0 / 6.0, 0x3fbf3f),
+            (3.0 / 6.0, 0x3fbfbf),
+            (4.0 / 6.0, 0x3f3fbf),
+            (5.0 / 6.0, 0xbf3fbf),
+            (1.0, 0xbf3f3f),
+        ],
+        classic: true,
+    },
+];
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::builtin(BUILTIN[0].name).expect("built-in palette")
+    }
+}
+
+impl Palette {
+    /// Builds a palette from `stops`, which are sorted by position.
+    ///
+    /// Returns `None` if there are no stops or a position lies outside of
+    /// `0..=1`.
+    pub fn new(name: impl Into<String>, mut stops: Vec<Stop>) -> Option<Self> {
+        if stops.is_empty() || !stops.iter().all(|s| (0.0..=1.0).contains(&s.position)) {
+            return None;
+        }
+        // Stable, so stops sharing a position keep their order and form a
+        // hard edge.
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        Some(Self {
+            name: name.into(),
+            stops,
+            interpolation: Interpolation::default(),
+            offset: 0.0,
+            scale: 1.0,
+            repeat: Repeat::default(),
+            classic: false,
+        })
+    }
+
+    /// Names of the built-in palettes, the default first.
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN.iter().map(|builtin| builtin.name)
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        let builtin = BUILTIN.iter().find(|builtin| builtin.name == name)?;
+        let stops = builtin
+            .stops
+            .iter()
+            .map(|&(position, rgb)| {
+                Stop::new(position, [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8])
+            })
+            .collect();
+        let mut palette = Self::new(builtin.name, stops)?;
+        palette.interpolation = builtin.interpolation;
+        palette.repeat = builtin.repeat;
+        palette.classic = builtin.classic;
+        Some(palette)
+    }
+
+    /// The built-in palette after this one, keeping `offset` and `scale`.
+    pub fn next(&self) -> Self {
+        let names: Vec<_> = Self::builtin_names().collect();
+        let next = match names.iter().position(|&name| name == self.name) {
+            Some(i) => names[(i + 1) % names.len()],
+            None => names[0],
+        };
+        Self {
+            offset: self.offset,
+            scale: self.scale,
+            ..Self::builtin(next).expect("built-in palette")
+        }
+    }
+
+    pub fn stops(&self) -> &[Stop] {
+        &self.stops
+    }
+
+    /// Color of the escape count `norm_steps`, normalized to `0..=1` by the
+    /// iteration limit.
+    pub fn color(&self, norm_steps: f64) -> [u8; 4] {
+        if self.classic {
+            // Hue in degrees, lightening from black to white towards the
+            // set, which is white. Unshifted and unscaled it's exactly the
+            // original mapping.
+            let hue = 360.0 * self.offset + self.scale * f64::powf(norm_steps * 360.0, 1.5);
+            return hsl_to_rgba(hue.rem_euclid(360.0), 50.0, norm_steps.min(1.0) * 100.0);
+        }
+        if norm_steps >= 1.0 {
+            return [0, 0, 0, 255];
+        }
+        // Grows quickly towards the set, so the bands near it stay
+        // distinguishable at any iteration limit.
+        let lengths = f64::powf(norm_steps * 360.0, 1.5) / 360.0;
+        let [r, g, b] = self.at(self.offset + self.scale * lengths);
+        [r, g, b, 255]
+    }
+
+    /// Color of an exterior point that escaped later than `fraction` of the
+    /// other exterior points, for histogram coloring. The fractions span the
+    /// gradient once before `scale` and `offset` apply.
+    pub fn equalized(&self, fraction: f64) -> [u8; 4] {
+        let [r, g, b] = self.at(self.offset + self.scale * fraction);
+        [r, g, b, 255]
+    }
+
+    /// Color of an orbit that came within `distance` of an orbit trap, for
+    /// trap coloring. One unit of distance spans the gradient once before
+    /// `scale` and `offset` apply.
+    pub fn trapped(&self, distance: f64) -> [u8; 4] {
+        let [r, g, b] = self.at(self.offset + self.scale * distance);
+        [r, g, b, 255]
+    }
+
+    /// Color of an escaped orbit whose orbit average is `average`, in
+    /// `0..=1`, for the averaging colorings. The range spans the gradient
+    /// once before `scale` and `offset` apply.
+    pub fn averaged(&self, average: f64) -> [u8; 4] {
+        let [r, g, b] = self.at(self.offset + self.scale * average);
+        [r, g, b, 255]
+    }
+
+    /// Color at `position` along the gradient, folded back onto `0..=1`
+    /// according to `repeat`.
+    pub fn at(&self, position: f64) -> [u8; 3] {
+        let t = match self.repeat {
+            Repeat::Wrap => position.rem_euclid(1.0),
+            Repeat::Mirror => 1.0 - (position.rem_euclid(2.0) - 1.0).abs(),
+            Repeat::Clamp => position.clamp(0.0, 1.0),
+        };
+        let next = self.stops.partition_point(|stop| stop.position <= t);
+        if next == 0 {
+            return self.stops[0].color;
+        }
+        let Some(b) = self.stops.get(next) else {
+            return self.stops[next - 1].color;
+        };
+        let a = self.stops[next - 1];
+        let frac = (t - a.position) / (b.position - a.position);
+        self.interpolation.mix(a.color, b.color, frac)
+    }
+}
+
+impl Interpolation {
+    /// Blends from `a` at `frac == 0` to `b` at `frac == 1`.
+    pub fn mix(self, a: [u8; 3], b: [u8; 3], frac: f64) -> [u8; 3] {
+        let lerp = |a: f64, b: f64| a + (b - a) * frac;
+        match self {
+            Interpolation::Rgb => {
+                std::array::from_fn(|i| lerp(a[i] as f64, b[i] as f64).round() as u8)
+            }
+            Interpolation::LinearRgb => std::array::from_fn(|i| {
+                linear_to_srgb(lerp(srgb_to_linear(a[i]), srgb_to_linear(b[i])))
+            }),
+            Interpolation::Oklab => {
+                let a = linear_to_oklab(a.map(srgb_to_linear));
+                let b = linear_to_oklab(b.map(srgb_to_linear));
+                oklab_to_linear(std::array::from_fn(|i| lerp(a[i], b[i]))).map(linear_to_srgb)
+            }
+        }
+    }
+}
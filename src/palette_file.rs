@@ -0,0 +1,451 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::palette::{Interpolation, Palette, Repeat, Stop};
+
+/// Failure to load a palette file.
+#[derive(Debug)]
+pub enum PaletteError {
+    Io(std::io::Error),
+    /// The file extension is none of `map`, `ggr`, `json` and `toml`.
+    UnknownFormat(String),
+    /// Malformed contents, with the 1-based line of the problem.
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaletteError::Io(err) => write!(f, "{err}"),
+            PaletteError::UnknownFormat(ext) => write!(
+                f,
+                "unknown palette format `{ext}`, expected `map`, `ggr`, `json` or `toml`"
+            ),
+            PaletteError::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {}
+
+fn parse_error<T>(line: usize, message: impl Into<String>) -> Result<T, PaletteError> {
+    Err(PaletteError::Parse {
+        line,
+        message: message.into(),
+    })
+}
+
+/// Builds the palette once all stops are read, reporting a problem with
+/// them at `line`.
+fn from_stops(name: &str, stops: Vec<Stop>, line: usize) -> Result<Palette, PaletteError> {
+    match Palette::new(name, stops) {
+        Some(palette) => Ok(palette),
+        None => parse_error(line, "expected at least one stop, positioned within 0..=1"),
+    }
+}
+
+impl Palette {
+    /// Loads a palette file, picking the format by extension: Fractint
+    /// `.map`, GIMP `.ggr`, or a `.json` or `.toml` stop list. The palette
+    /// is named after the file unless the file names it.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PaletteError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(PaletteError::Io)?;
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = path.extension().unwrap_or_default().to_string_lossy();
+        match ext.to_ascii_lowercase().as_str() {
+            "map" => Self::from_map(&name, &text),
+            "ggr" => Self::from_ggr(&text),
+            "json" => Self::from_json(&name, &text),
+            "toml" => Self::from_toml(&name, &text),
+            _ => Err(PaletteError::UnknownFormat(ext.into_owned())),
+        }
+    }
+
+    /// Parses a Fractint color map: one `R G B` line of 0–255 values per
+    /// color, spaced evenly along the gradient. Anything after the third
+    /// value is a comment.
+    pub fn from_map(name: &str, text: &str) -> Result<Self, PaletteError> {
+        let mut colors = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let values: Vec<_> = line.split_whitespace().take(3).collect();
+            match values.len() {
+                0 => continue,
+                3 => {}
+                _ => return parse_error(i + 1, "expected `R G B`"),
+            }
+            let mut color = [0; 3];
+            for (channel, value) in color.iter_mut().zip(values) {
+                *channel = match value.parse() {
+                    Ok(v) => v,
+                    Err(_) => return parse_error(i + 1, format!("invalid color value `{value}`")),
+                };
+            }
+            colors.push(color);
+        }
+        let last = colors.len().saturating_sub(1).max(1) as f64;
+        let stops = colors
+            .into_iter()
+            .enumerate()
+            .map(|(i, color)| Stop::new(i as f64 / last, color))
+            .collect();
+        let mut palette = from_stops(name, stops, text.lines().count())?;
+        palette.interpolation = Interpolation::Rgb;
+        Ok(palette)
+    }
+
+    /// Parses a GIMP gradient.
+    ///
+    /// Every segment becomes stops at its ends and at its midpoint, which
+    /// reproduces linear RGB segments exactly. Curved, sine, spherical and
+    /// HSV segments are approximated the same way, and opacity is ignored.
+    pub fn from_ggr(text: &str) -> Result<Self, PaletteError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()));
+        match lines.next() {
+            Some((_, "GIMP Gradient")) => {}
+            _ => return parse_error(1, "expected `GIMP Gradient`"),
+        }
+        let mut name = String::from("gimp");
+        let mut count = None;
+        for (n, line) in lines.by_ref() {
+            if let Some(rest) = line.strip_prefix("Name:") {
+                name = rest.trim().to_string();
+                continue;
+            }
+            match line.parse::<usize>() {
+                Ok(c) => count = Some((n, c)),
+                Err(_) => return parse_error(n, "expected the number of segments"),
+            }
+            break;
+        }
+        let Some((mut n, count)) = count else {
+            return parse_error(1, "missing the number of segments");
+        };
+        let mut stops = vec![];
+        for _ in 0..count {
+            let Some((line, segment)) = lines.next() else {
+                return parse_error(n + 1, format!("expected {count} segments"));
+            };
+            n = line;
+            let values: Vec<f64> = match segment.split_whitespace().map(str::parse).collect() {
+                Ok(values) => values,
+                Err(_) => return parse_error(n, "invalid segment value"),
+            };
+            if values.len() < 11 {
+                return parse_error(n, "expected positions and two RGBA colors");
+            }
+            let channel = |v: f64| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+            let left = [values[3], values[4], values[5]];
+            let right = [values[7], values[8], values[9]];
+            let middle = std::array::from_fn(|i| (left[i] + right[i]) / 2.0);
+            for (position, color) in [(values[0], left), (values[1], middle), (values[2], right)] {
+                stops.push(Stop::new(position, color.map(channel)));
+            }
+        }
+        let mut palette = from_stops(&name, stops, n)?;
+        palette.interpolation = Interpolation::Rgb;
+        Ok(palette)
+    }
+
+    /// Parses a JSON stop list:
+    ///
+    /// ```json
+    /// {
+    ///   "interpolation": "oklab",
+    ///   "stops": [
+    ///     { "position": 0.0, "color": "#000764" },
+    ///     { "position": 1.0, "color": "#edffff" }
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// `name`, `interpolation` (`rgb`, `linear-rgb` or `oklab`), `offset`,
+    /// `scale` and `repeat` (`wrap`, `mirror` or `clamp`) are optional.
+    pub fn from_json(name: &str, text: &str) -> Result<Self, PaletteError> {
+        let mut parser = JsonParser { text, pos: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos != text.len() {
+            return parser.error("unexpected trailing characters");
+        }
+        from_value(name, value, line_of(text, text.len()))
+    }
+
+    /// Parses a TOML stop list with the same keys as
+    /// [`from_json`](Self::from_json), with one `[[stops]]` table per stop:
+    ///
+    /// ```toml
+    /// interpolation = "oklab"
+    ///
+    /// [[stops]]
+    /// position = 0.0
+    /// color = "#000764"
+    /// ```
+    ///
+    /// Only string and number values are supported.
+    pub fn from_toml(name: &str, text: &str) -> Result<Self, PaletteError> {
+        let mut root = vec![];
+        let mut stops = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let n = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') {
+                if line != "[[stops]]" {
+                    return parse_error(n, format!("unsupported table `{line}`"));
+                }
+                stops.push(Value::Object(vec![], n));
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return parse_error(n, "expected `key = value`");
+            };
+            let key = key.trim().trim_matches('"').to_string();
+            let mut parser = JsonParser {
+                text: value,
+                pos: 0,
+            };
+            let value = parser.value().map_err(|_| PaletteError::Parse {
+                line: n,
+                message: format!("invalid value for `{key}`"),
+            })?;
+            parser.skip_whitespace();
+            if !matches!(parser.rest().chars().next(), None | Some('#')) {
+                return parse_error(n, format!("unexpected characters after `{key}`"));
+            }
+            match stops.last_mut() {
+                Some(Value::Object(fields, _)) => fields.push((key, value)),
+                _ => root.push((key, value)),
+            }
+        }
+        root.push(("stops".to_string(), Value::Array(stops)));
+        from_value(name, Value::Object(root, 1), text.lines().count())
+    }
+}
+
+/// Builds a palette from a parsed JSON or TOML document.
+fn from_value(name: &str, value: Value, last_line: usize) -> Result<Palette, PaletteError> {
+    let Value::Object(fields, line) = value else {
+        return parse_error(1, "expected an object");
+    };
+    let mut name = name.to_string();
+    let mut stops = vec![];
+    let mut interpolation = None;
+    let mut offset = None;
+    let mut scale = None;
+    let mut repeat = None;
+    for (key, value) in fields {
+        match (key.as_str(), value) {
+            ("name", Value::String(s)) => name = s,
+            ("interpolation", Value::String(s)) => {
+                interpolation = Some(match s.as_str() {
+                    "rgb" => Interpolation::Rgb,
+                    "linear-rgb" => Interpolation::LinearRgb,
+                    "oklab" => Interpolation::Oklab,
+                    _ => return parse_error(line, format!("unknown interpolation `{s}`")),
+                })
+            }
+            ("offset", Value::Number(v)) if v.is_finite() => offset = Some(v),
+            ("scale", Value::Number(v)) if v.is_finite() && v > 0.0 => scale = Some(v),
+            ("repeat", Value::String(s)) => {
+                repeat = Some(match s.as_str() {
+                    "wrap" => Repeat::Wrap,
+                    "mirror" => Repeat::Mirror,
+                    "clamp" => Repeat::Clamp,
+                    _ => return parse_error(line, format!("unknown repeat mode `{s}`")),
+                })
+            }
+            ("stops", Value::Array(items)) => {
+                for item in items {
+                    stops.push(stop_from_value(item)?);
+                }
+            }
+            (key, _) => return parse_error(line, format!("invalid or unknown key `{key}`")),
+        }
+    }
+    let mut palette = from_stops(&name, stops, last_line)?;
+    palette.interpolation = interpolation.unwrap_or_default();
+    palette.offset = offset.unwrap_or(palette.offset);
+    palette.scale = scale.unwrap_or(palette.scale);
+    palette.repeat = repeat.unwrap_or_default();
+    Ok(palette)
+}
+
+fn stop_from_value(value: Value) -> Result<Stop, PaletteError> {
+    let Value::Object(fields, line) = value else {
+        return parse_error(1, "expected a stop object");
+    };
+    let mut position = None;
+    let mut color = None;
+    for (key, value) in fields {
+        match (key.as_str(), value) {
+            ("position", Value::Number(v)) => position = Some(v),
+            ("color", Value::String(s)) => match parse_hex_color(&s) {
+                Some(c) => color = Some(c),
+                None => {
+                    return parse_error(line, format!("invalid color `{s}`, expected `#rrggbb`"))
+                }
+            },
+            (key, _) => return parse_error(line, format!("invalid or unknown stop key `{key}`")),
+        }
+    }
+    match (position, color) {
+        (Some(position), Some(color)) => Ok(Stop::new(position, color)),
+        _ => parse_error(line, "a stop needs a `position` and a `color`"),
+    }
+}
+
+fn parse_hex_color(s: &str) -> Option<[u8; 3]> {
+    let hex = s.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+fn line_of(text: &str, pos: usize) -> usize {
+    text[..pos].matches('\n').count() + 1
+}
+
+/// Parsed JSON value. Objects keep their key order and the line they start
+/// on, for error messages.
+#[derive(Debug)]
+enum Value {
+    /// `true`, `false` or `null`, which no palette key takes.
+    Literal,
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>, usize),
+}
+
+/// Minimal recursive descent JSON parser, enough for palette files.
+struct JsonParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, PaletteError> {
+        parse_error(line_of(self.text, self.pos), message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes `token` after any whitespace, if it comes next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let found = self.rest().starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    fn value(&mut self) -> Result<Value, PaletteError> {
+        self.skip_whitespace();
+        let line = line_of(self.text, self.pos);
+        if self.eat("{") {
+            let mut fields = vec![];
+            if self.eat("}") {
+                return Ok(Value::Object(fields, line));
+            }
+            loop {
+                self.skip_whitespace();
+                let key = self.string()?;
+                if !self.eat(":") {
+                    return self.error("expected `:`");
+                }
+                fields.push((key, self.value()?));
+                if self.eat("}") {
+                    return Ok(Value::Object(fields, line));
+                }
+                if !self.eat(",") {
+                    return self.error("expected `,` or `}`");
+                }
+            }
+        }
+        if self.eat("[") {
+            let mut items = vec![];
+            if self.eat("]") {
+                return Ok(Value::Array(items));
+            }
+            loop {
+                items.push(self.value()?);
+                if self.eat("]") {
+                    return Ok(Value::Array(items));
+                }
+                if !self.eat(",") {
+                    return self.error("expected `,` or `]`");
+                }
+            }
+        }
+        if self.rest().starts_with('"') {
+            return self.string().map(Value::String);
+        }
+        for literal in ["true", "false", "null"] {
+            if self.eat(literal) {
+                return Ok(Value::Literal);
+            }
+        }
+        let len = self
+            .rest()
+            .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+            .unwrap_or(self.rest().len());
+        match self.rest()[..len].parse() {
+            Ok(v) if len > 0 => {
+                self.pos += len;
+                Ok(Value::Number(v))
+            }
+            _ => self.error("expected a value"),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, PaletteError> {
+        if !self.rest().starts_with('"') {
+            return self.error("expected a string");
+        }
+        self.pos += 1;
+        let mut s = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(s);
+                }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => {
+                        let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                        match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                            Some(c) => s.push(c),
+                            None => return self.error("invalid unicode escape"),
+                        }
+                    }
+                    Some(c @ ('"' | '\\' | '/')) => s.push(c),
+                    _ => return self.error("invalid escape"),
+                },
+                c => s.push(c),
+            }
+        }
+        self.error("unterminated string")
+    }
+}
@@ -0,0 +1,115 @@
+use num::complex::Complex;
+
+use crate::bignum::BigComplex;
+use crate::formula::Formula;
+use crate::kernel::{interior, smooth_escape, Escape, Recorder, Statistics};
+
+/// Orbit of a single reference point, iterated in arbitrary precision and
+/// rounded to `f64` for perturbation rendering.
+#[derive(Clone, Debug)]
+pub struct ReferenceOrbit {
+    orbit: Vec<Complex<f64>>,
+    c: Complex<f64>,
+}
+
+impl ReferenceOrbit {
+    /// Iterates `formula` from `z` with the parameter `c` until it escapes or
+    /// `max_iters` steps were taken.
+    ///
+    /// Returns `None` if `formula` has no [`Formula::step_big`].
+    pub fn compute<F: Formula + ?Sized>(
+        formula: &F,
+        mut z: BigComplex,
+        c: &BigComplex,
+        max_iters: usize,
+    ) -> Option<Self> {
+        let mut orbit = Vec::with_capacity(max_iters + 1);
+        for _ in 0..=max_iters {
+            let zf = z.to_f64();
+            if formula.escaped(zf) {
+                break;
+            }
+            orbit.push(zf);
+            z = formula.step_big(&z, c)?;
+        }
+        Some(Self {
+            orbit,
+            c: c.to_f64(),
+        })
+    }
+
+    /// Number of stored iterates.
+    pub fn len(&self) -> usize {
+        self.orbit.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orbit.is_empty()
+    }
+
+    /// Iterates the point whose orbit starts `dz` away from the reference and
+    /// whose parameter is `dc` away from the reference parameter, tracking
+    /// only the difference to the reference in `f64`.
+    ///
+    /// When the full value gets smaller than the difference, precision in the
+    /// difference is about to be lost (the pixel would glitch); the orbit is
+    /// then rebased onto the start of the reference orbit. This also covers
+    /// references that escape before the pixel does.
+    ///
+    /// Returns the escape count and the number of rebases, or `None` if
+    /// `formula` has no [`Formula::step_delta`] or the reference escapes
+    /// within a step, too soon to rebase onto.
+    pub fn escape<F: Formula + ?Sized>(
+        &self,
+        formula: &F,
+        dz: Complex<f64>,
+        dc: Complex<f64>,
+        max_iters: usize,
+    ) -> Option<(Escape, usize)> {
+        self.escape_tracked(formula, dz, dc, max_iters, Statistics::default())
+    }
+
+    /// [`escape`](Self::escape) that also records `statistics` of the full
+    /// orbit.
+    pub(crate) fn escape_tracked<F: Formula + ?Sized>(
+        &self,
+        formula: &F,
+        mut dz: Complex<f64>,
+        dc: Complex<f64>,
+        max_iters: usize,
+        statistics: Statistics,
+    ) -> Option<(Escape, usize)> {
+        if self.orbit.len() < 2 {
+            // Only possible if the reference starts outside the escape radius
+            // or escapes after its first step. Rebasing needs a second
+            // iterate to step onto.
+            return None;
+        }
+        let mut rebases = 0;
+        let mut m = 0;
+        let mut z = self.orbit[0] + dz;
+        let mut recorder = Recorder::new(statistics, self.c + dc);
+        for i in 0..=max_iters {
+            z = self.orbit[m] + dz;
+            if i > 0 {
+                recorder.visit(z);
+            }
+            if formula.escaped(z) {
+                let escape = smooth_escape(formula, z, self.c + dc, i, max_iters, recorder);
+                return Some((escape, rebases));
+            }
+            if i == max_iters {
+                break;
+            }
+            recorder.step(formula, z);
+            if m + 1 == self.orbit.len() || z.norm_sqr() < dz.norm_sqr() {
+                dz = z - self.orbit[0];
+                m = 0;
+                rebases += 1;
+            }
+            dz = formula.step_delta(self.orbit[m], dz, dc)?;
+            m += 1;
+        }
+        Some((recorder.finish(interior(z, max_iters)), rebases))
+    }
+}
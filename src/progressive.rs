@@ -0,0 +1,231 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use mandelbrot::{MandelbrotGrid, UpdateStats};
+
+/// Resolution divisors of the passes of every render, coarsest first.
+const PASSES: [usize; 3] = [8, 2, 1];
+
+/// Image published by the worker, always at the full grid size.
+#[derive(Debug)]
+pub struct Frame {
+    /// Request this frame belongs to.
+    generation: u64,
+    /// RGBA pixels, upscaled if the pass was coarser than the grid.
+    pub pixels: Vec<u8>,
+    /// Set for the last, full resolution pass.
+    pub stats: Option<UpdateStats>,
+}
+
+struct Job {
+    generation: u64,
+    width: usize,
+    height: usize,
+    /// View and settings to render, without cells.
+    settings: MandelbrotGrid,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Renders grids on a background thread, coarse preview first.
+///
+/// Every request cancels the one before it, so only the latest view is ever
+/// worked on. Each finished pass replaces the frame returned by
+/// [`latest`](Self::latest). The full resolution grid is kept between
+/// requests, so a pan only computes the newly exposed pixels and skips the
+/// preview.
+pub struct Renderer {
+    jobs: Sender<Job>,
+    cancel: Arc<AtomicBool>,
+    generation: u64,
+    latest: Arc<Mutex<Option<Frame>>>,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        let (jobs, queue) = mpsc::channel();
+        let latest = Arc::new(Mutex::new(None));
+        let published = latest.clone();
+        thread::spawn(move || work(queue, published));
+        Self {
+            jobs,
+            cancel: Arc::new(AtomicBool::new(false)),
+            generation: 0,
+            latest,
+        }
+    }
+
+    /// Starts rendering the view and settings of `grid`, cancelling the
+    /// render in progress.
+    pub fn render(&mut self, grid: &MandelbrotGrid) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.cancel = Arc::new(AtomicBool::new(false));
+        self.generation += 1;
+        let job = Job {
+            generation: self.generation,
+            width: grid.width(),
+            height: grid.height(),
+            settings: grid.resized(0, 0),
+            cancel: self.cancel.clone(),
+        };
+        // The worker only stops when the renderer is dropped.
+        self.jobs.send(job).expect("render worker exited");
+    }
+
+    /// Takes the most recent frame of the current request, if a pass
+    /// finished since the last call.
+    pub fn latest(&self) -> Option<Frame> {
+        let frame = self.latest.lock().expect("render worker panicked").take()?;
+        (frame.generation == self.generation).then_some(frame)
+    }
+}
+
+fn work(queue: Receiver<Job>, latest: Arc<Mutex<Option<Frame>>>) {
+    let mut full: Option<MandelbrotGrid> = None;
+    while let Ok(mut job) = queue.recv() {
+        // Skip straight to the newest request if several piled up.
+        while let Ok(newer) = queue.try_recv() {
+            job = newer;
+        }
+        let (width, height) = (job.width, job.height);
+        let mut grid = match full.take() {
+            Some(mut grid) if grid.width() == width && grid.height() == height => {
+                grid.copy_settings(&job.settings);
+                grid
+            }
+            _ => job.settings.resized(width, height),
+        };
+        let passes = match grid.reusable_shift() {
+            Some(_) => &PASSES[PASSES.len() - 1..],
+            None => &PASSES[..],
+        };
+        for &scale in passes {
+            let mut coarse;
+            let pass = if scale == 1 {
+                &mut grid
+            } else {
+                coarse = job
+                    .settings
+                    .resized((width / scale).max(1), (height / scale).max(1));
+                // Previews are upscaled anyway, so don't antialias them.
+                coarse.supersampling = None;
+                &mut coarse
+            };
+            let Some(stats) = pass.update_cancellable(&job.cancel) else {
+                break;
+            };
+            let mut pixels = vec![0; 4 * pass.width() * pass.height()];
+            pass.draw(&mut pixels);
+            if scale != 1 {
+                pixels = upscale(&pixels, pass.width(), pass.height(), width, height);
+            }
+            if job.cancel.load(Ordering::Relaxed) {
+                break;
+            }
+            *latest.lock().expect("viewer panicked") = Some(Frame {
+                generation: job.generation,
+                pixels,
+                stats: (scale == 1).then_some(stats),
+            });
+        }
+        full = Some(grid);
+    }
+}
+
+/// Nearest neighbour upscaling of an RGBA image.
+fn upscale(
+    src: &[u8],
+    src_width: usize,
+    src_height: usize,
+    width: usize,
+    height: usize,
+) -> Vec<u8> {
+    let mut dst = vec![0; 4 * width * height];
+    for (y, row) in dst.chunks_exact_mut(4 * width).enumerate() {
+        let src_y = (y * src_height / height).min(src_height - 1);
+        for (x, pix) in row.chunks_exact_mut(4).enumerate() {
+            let src_x = (x * src_width / width).min(src_width - 1);
+            let idx = 4 * (src_x + src_y * src_width);
+            pix.copy_from_slice(&src[idx..idx + 4]);
+        }
+    }
+    dst
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use mandelbrot::Viewport;
+
+    use super::*;
+
+    /// Collects the frames of the current request up to the full
+    /// resolution one.
+    fn collect_frames(renderer: &Renderer) -> Vec<Frame> {
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let mut frames = Vec::new();
+        while frames
+            .last()
+            .is_none_or(|frame: &Frame| frame.stats.is_none())
+        {
+            assert!(Instant::now() < deadline, "render did not finish");
+            match renderer.latest() {
+                Some(frame) => frames.push(frame),
+                None => thread::sleep(Duration::from_millis(1)),
+            }
+        }
+        frames
+    }
+
+    fn drawn(grid: &MandelbrotGrid) -> Vec<u8> {
+        let mut grid = grid.resized(grid.width(), grid.height());
+        grid.update();
+        let mut pixels = vec![0; 4 * grid.width() * grid.height()];
+        grid.draw(&mut pixels);
+        pixels
+    }
+
+    #[test]
+    fn last_pass_matches_a_synchronous_render() {
+        let mut grid = MandelbrotGrid::new(64, 48);
+        grid.viewport.fit(64, 48);
+        let mut renderer = Renderer::new();
+        renderer.render(&grid);
+        let frames = collect_frames(&renderer);
+        assert!(frames.iter().all(|frame| frame.pixels.len() == 4 * 64 * 48));
+        assert!(frames.last().unwrap().pixels == drawn(&grid));
+
+        // A pan reuses the full grid and skips the previews.
+        grid.viewport.pan(4.0 / 64.0, 0.0);
+        renderer.render(&grid);
+        let frames = collect_frames(&renderer);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].stats.as_ref().unwrap().reused_pixels, 60 * 48);
+        assert!(frames[0].pixels == drawn(&grid));
+    }
+
+    #[test]
+    fn new_request_replaces_the_one_in_progress() {
+        let mut grid = MandelbrotGrid::new(64, 64);
+        let mut renderer = Renderer::new();
+        renderer.render(&grid);
+        grid.viewport = Viewport::centered(-0.75, 0.1, 0.05);
+        renderer.render(&grid);
+        let frames = collect_frames(&renderer);
+        assert!(frames.iter().all(|frame| frame.generation == 2));
+        assert!(frames.last().unwrap().pixels == drawn(&grid));
+    }
+
+    #[test]
+    fn upscale_repeats_the_nearest_pixel() {
+        let src: Vec<u8> = (0..4 * 2 * 2).collect();
+        let dst = upscale(&src, 2, 2, 4, 3);
+        let pixel = |x: usize, y: usize| dst[4 * (x + y * 4)];
+        let rows: Vec<[u8; 4]> = (0..3)
+            .map(|y| [pixel(0, y), pixel(1, y), pixel(2, y), pixel(3, y)])
+            .collect();
+        assert_eq!(rows, [[0, 0, 4, 4], [0, 0, 4, 4], [8, 8, 12, 12]]);
+    }
+}
@@ -0,0 +1,125 @@
+use num::complex::Complex;
+use wide::{f64x4, CmpGt, CmpLt};
+
+use crate::formula::Mandelbrot;
+use crate::kernel::{
+    interior, smooth_escape, Escape, Recorder, Shortcut, Statistics, PERIODICITY_EPSILON,
+};
+
+/// Number of orbits iterated together by [`escape_lanes`].
+pub const LANES: usize = 4;
+
+/// Whether [`escape_lanes`] was compiled to vector instructions. This is
+/// decided at compile time, not by the CPU running the program: `wide` picks
+/// its backend from the target features the build enables, and without one
+/// the lanes are emulated and the scalar kernel is faster.
+pub fn simd_compiled() -> bool {
+    cfg!(any(
+        target_feature = "sse2",
+        target_feature = "simd128",
+        all(target_arch = "aarch64", target_feature = "neon")
+    ))
+}
+
+/// Iterates `z^2 + c` for [`LANES`] orbits at once, starting each orbit at
+/// its `z` with its parameter `c`.
+///
+/// Orbits are tested against the squared escape radius, so no square root is
+/// taken, and lanes that escaped or became periodic are masked out until
+/// every lane is done or hit `max_iters`. The results match
+/// [`crate::escape_with`] with [`Mandelbrot`].
+pub fn escape_lanes(
+    z: [Complex<f64>; LANES],
+    c: [Complex<f64>; LANES],
+    max_iters: usize,
+) -> [Escape; LANES] {
+    let cr = f64x4::from(c.map(|c| c.re));
+    let ci = f64x4::from(c.map(|c| c.im));
+    let mut zr = f64x4::from(z.map(|z| z.re));
+    let mut zi = f64x4::from(z.map(|z| z.im));
+    let radius_sqr = f64x4::splat(4.0);
+    let epsilon = f64x4::splat(PERIODICITY_EPSILON);
+    let mut active = (1 << LANES) - 1;
+    let mut results = [None; LANES];
+    // Brent's periodicity check of `iterate`, per lane: the orbit is
+    // compared with the iterate saved after `saved_at` steps, which is
+    // replaced whenever `window` steps passed or the orbit returned to it.
+    let (mut saved_r, mut saved_i) = (zr, zi);
+    let mut saved_at = [0; LANES];
+    let mut window = [1; LANES];
+    let mut last_return = [None; LANES];
+    let mut next_save = 1;
+    for i in 0..=max_iters {
+        let rr = zr * zr;
+        let ii = zi * zi;
+        let escaped = (rr + ii).cmp_gt(radius_sqr).move_mask() & active;
+        if escaped != 0 {
+            let (re, im) = (zr.to_array(), zi.to_array());
+            for lane in (0..LANES).filter(|lane| escaped & (1 << lane) != 0) {
+                let z = Complex::new(re[lane], im[lane]);
+                let recorder = Recorder::new(Statistics::default(), c[lane]);
+                results[lane] = Some(smooth_escape(
+                    &Mandelbrot,
+                    z,
+                    c[lane],
+                    i,
+                    max_iters,
+                    recorder,
+                ));
+            }
+            active &= !escaped;
+            if active == 0 {
+                break;
+            }
+        }
+        let ri = zr * zi;
+        zi = ri + ri + ci;
+        zr = rr - ii + cr;
+
+        let steps = i + 1;
+        let (dr, di) = (zr - saved_r, zi - saved_i);
+        let distance = dr * dr + di * di;
+        let returned = distance.cmp_lt(epsilon).move_mask() & active;
+        if returned == 0 && steps != next_save {
+            continue;
+        }
+        let (re, im, distance) = (zr.to_array(), zi.to_array(), distance.to_array());
+        let (mut sr, mut si) = (saved_r.to_array(), saved_i.to_array());
+        let running = active;
+        for lane in (0..LANES).filter(|lane| running & (1 << lane) != 0) {
+            let since_saved = steps - saved_at[lane];
+            if returned & (1 << lane) != 0 {
+                // Only stop once a second return shows the cycle attracting,
+                // as `iterate` does.
+                if last_return[lane].is_some_and(|last| distance[lane] <= last) {
+                    let z = Complex::new(re[lane], im[lane]);
+                    results[lane] = Some(Escape {
+                        shortcut: Some(Shortcut::Period(since_saved)),
+                        ..interior(z, max_iters)
+                    });
+                    active &= !(1 << lane);
+                    continue;
+                }
+                last_return[lane] = Some(distance[lane]);
+            } else if since_saved == window[lane] {
+                window[lane] *= 2;
+            } else {
+                continue;
+            }
+            (sr[lane], si[lane], saved_at[lane]) = (re[lane], im[lane], steps);
+        }
+        if active == 0 {
+            break;
+        }
+        (saved_r, saved_i) = (f64x4::from(sr), f64x4::from(si));
+        next_save = (0..LANES)
+            .filter(|lane| active & (1 << lane) != 0)
+            .map(|lane| saved_at[lane] + window[lane])
+            .min()
+            .unwrap_or(usize::MAX);
+    }
+    let (re, im) = (zr.to_array(), zi.to_array());
+    std::array::from_fn(|lane| {
+        results[lane].unwrap_or_else(|| interior(Complex::new(re[lane], im[lane]), max_iters))
+    })
+}
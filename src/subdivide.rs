@@ -0,0 +1,149 @@
+use crate::grid::eval_indices;
+
+/// Rectangles whose inside is narrower than this are computed pixel by pixel,
+/// as their border would cost about as much as the pixels it could save.
+const MIN_FILL_SIZE: usize = 4;
+
+/// Rectangle of pixels with inclusive bounds whose border has been computed.
+#[derive(Clone, Copy, Debug)]
+struct Rect {
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+}
+
+impl Rect {
+    fn border(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let top = (self.x0..=self.x1).map(move |x| (x, self.y0));
+        let bottom = (self.x0..=self.x1).map(move |x| (x, self.y1));
+        let left = (self.y0 + 1..self.y1).map(move |y| (self.x0, y));
+        let right = (self.y0 + 1..self.y1).map(move |y| (self.x1, y));
+        top.chain(bottom).chain(left).chain(right)
+    }
+
+    fn inside(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        itertools::iproduct!(self.y0 + 1..self.y1, self.x0 + 1..self.x1).map(|(y, x)| (x, y))
+    }
+
+    /// Whether the fractional pixel position `(x, y)` lies within a pixel of
+    /// the rectangle.
+    fn contains(&self, (x, y): (f64, f64)) -> bool {
+        let (x0, y0) = (self.x0 as f64 - 1.0, self.y0 as f64 - 1.0);
+        let (x1, y1) = (self.x1 as f64 + 2.0, self.y1 as f64 + 2.0);
+        (x0..=x1).contains(&x) && (y0..=y1).contains(&y)
+    }
+
+    /// Splits into four quarters that share their middle row and column.
+    fn quarters(&self) -> [Rect; 4] {
+        let mx = (self.x0 + self.x1) / 2;
+        let my = (self.y0 + self.y1) / 2;
+        [
+            Rect {
+                x1: mx,
+                y1: my,
+                ..*self
+            },
+            Rect {
+                x0: mx,
+                y1: my,
+                ..*self
+            },
+            Rect {
+                x1: mx,
+                y0: my,
+                ..*self
+            },
+            Rect {
+                x0: mx,
+                y0: my,
+                ..*self
+            },
+        ]
+    }
+}
+
+/// Evaluates a `width`x`height` grid with the Mariani–Silver algorithm.
+///
+/// The grid is split into rectangles recursively. Only the borders of each
+/// rectangle are evaluated, and a rectangle whose border is `uniform` is
+/// filled with the border value instead of being evaluated. `uniform` only
+/// holds for values that can't enclose different ones, which for escape
+/// times are those that escaped: the points escaping at a given iteration
+/// form rings around a connected set, so a region bounded by one such ring
+/// escapes at that iteration as well unless the set lies inside it. Hence
+/// rectangles containing `anchor`, the fractional pixel position of a point
+/// of the set, are never filled. `eval` is handed batches of `(x, y)` pixels
+/// and writes their values into its second argument.
+///
+/// Returns the value of every pixel in row-major order together with whether
+/// it was filled rather than evaluated.
+pub(crate) fn subdivide<T, E, U>(
+    width: usize,
+    height: usize,
+    anchor: (f64, f64),
+    eval: E,
+    uniform: U,
+) -> (Vec<T>, Vec<bool>)
+where
+    T: Copy + Default + Send + Sync,
+    E: Fn(&[(usize, usize)], &mut [T]) + Sync,
+    U: Fn(&T, &T) -> bool,
+{
+    let mut values = vec![T::default(); width * height];
+    // Pixels that are evaluated or queued for evaluation.
+    let mut known = vec![false; width * height];
+    let mut filled = vec![false; width * height];
+    if width == 0 || height == 0 {
+        return (values, filled);
+    }
+    let mut rects = vec![Rect {
+        x0: 0,
+        y0: 0,
+        x1: width - 1,
+        y1: height - 1,
+    }];
+    let mut pending = vec![];
+    while !rects.is_empty() || !pending.is_empty() {
+        // Evaluate every border pixel of this level in one parallel batch.
+        let mut todo = std::mem::take(&mut pending);
+        for (x, y) in rects.iter().flat_map(Rect::border) {
+            let idx = x + y * width;
+            if !known[idx] {
+                known[idx] = true;
+                todo.push(idx);
+            }
+        }
+        let results = eval_indices(width, &todo, &eval);
+        for (&idx, value) in todo.iter().zip(results) {
+            values[idx] = value;
+        }
+
+        let mut next = vec![];
+        for rect in rects {
+            if rect.x1 - rect.x0 <= MIN_FILL_SIZE || rect.y1 - rect.y0 <= MIN_FILL_SIZE {
+                for (x, y) in rect.inside() {
+                    known[x + y * width] = true;
+                    pending.push(x + y * width);
+                }
+                continue;
+            }
+            let first = values[rect.x0 + rect.y0 * width];
+            let is_uniform = !rect.contains(anchor)
+                && rect
+                    .border()
+                    .all(|(x, y)| uniform(&first, &values[x + y * width]));
+            if is_uniform {
+                for (x, y) in rect.inside() {
+                    values[x + y * width] = first;
+                    known[x + y * width] = true;
+                    filled[x + y * width] = true;
+                }
+            } else {
+                next.extend(rect.quarters());
+            }
+        }
+        rects = next;
+    }
+    (values, filled)
+}
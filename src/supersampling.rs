@@ -0,0 +1,126 @@
+use crate::color::{linear_to_srgb, srgb_to_linear};
+
+/// Largest difference of any channel between neighbouring pixels, as a
+/// fraction of the full range, that the adaptive mode leaves unsampled by
+/// default.
+pub const DEFAULT_THRESHOLD: f64 = 0.1;
+
+/// Placement of the samples within a pixel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Pattern {
+    /// Samples at the centers of a regular grid over the pixel.
+    #[default]
+    Grid,
+    /// One sample at a random position in every cell of the grid, which
+    /// turns the moiré of fine periodic detail into noise.
+    Jittered,
+}
+
+/// Antialiasing by averaging several samples per pixel.
+///
+/// The samples are colored like pixels and averaged in linear light, so
+/// edges between bright and dark areas don't come out too dark.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Supersampling {
+    pub pattern: Pattern,
+    /// Samples per pixel along each axis, `factor * factor` in all.
+    pub factor: usize,
+    /// Only supersample pixels whose color differs from one of their eight
+    /// neighbours by more than this fraction in any channel, or every pixel
+    /// if `None`.
+    pub threshold: Option<f64>,
+}
+
+impl Default for Supersampling {
+    fn default() -> Self {
+        Self {
+            pattern: Pattern::default(),
+            factor: 3,
+            threshold: Some(DEFAULT_THRESHOLD),
+        }
+    }
+}
+
+impl Supersampling {
+    /// Positions of the samples of pixel `(x, y)` relative to its top left
+    /// corner, in pixels.
+    pub fn offsets(&self, x: usize, y: usize) -> impl Iterator<Item = (f64, f64)> + '_ {
+        let n = self.factor;
+        (0..n * n).map(move |i| {
+            let (u, v) = match self.pattern {
+                Pattern::Grid => (0.5, 0.5),
+                Pattern::Jittered => {
+                    let seed = mix(mix(mix(x as u64) ^ y as u64) ^ i as u64);
+                    (unit(seed), unit(mix(seed)))
+                }
+            };
+            (
+                ((i % n) as f64 + u) / n as f64,
+                ((i / n) as f64 + v) / n as f64,
+            )
+        })
+    }
+
+    /// Whether a pixel colored `a` next to one colored `b` is supersampled,
+    /// which without a threshold it always is.
+    pub fn differs(&self, a: [u8; 4], b: [u8; 4]) -> bool {
+        let Some(threshold) = self.threshold else {
+            return true;
+        };
+        let threshold = threshold * 255.0;
+        a.iter()
+            .zip(b)
+            .any(|(&a, b)| a.abs_diff(b) as f64 > threshold)
+    }
+
+    /// The supersampling after this one when cycling through them in the
+    /// viewer: off, adaptive, every pixel and jittered.
+    pub fn next(supersampling: Option<Self>) -> Option<Self> {
+        match supersampling {
+            None => Some(Self::default()),
+            Some(s) if s.threshold.is_some() => Some(Self {
+                threshold: None,
+                ..s
+            }),
+            Some(s) if s.pattern == Pattern::Grid => Some(Self {
+                pattern: Pattern::Jittered,
+                ..s
+            }),
+            Some(_) => None,
+        }
+    }
+}
+
+/// Mean of sRGB `samples`, taken in linear light.
+pub(crate) fn average(samples: impl Iterator<Item = [u8; 4]>) -> [u8; 4] {
+    let mut sum = [0.0; 4];
+    let mut count = 0;
+    for [r, g, b, a] in samples {
+        sum[0] += srgb_to_linear(r);
+        sum[1] += srgb_to_linear(g);
+        sum[2] += srgb_to_linear(b);
+        // Alpha is linear already.
+        sum[3] += a as f64 / 255.0;
+        count += 1;
+    }
+    let [r, g, b, a] = sum.map(|sum| sum / count.max(1) as f64);
+    [
+        linear_to_srgb(r),
+        linear_to_srgb(g),
+        linear_to_srgb(b),
+        (a * 255.0).round() as u8,
+    ]
+}
+
+/// SplitMix64 finalizer, a cheap hash of the sample index for jittering.
+fn mix(mut h: u64) -> u64 {
+    h = h.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+/// Maps a hash onto `0..1`.
+fn unit(h: u64) -> f64 {
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
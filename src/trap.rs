@@ -0,0 +1,108 @@
+use std::fmt;
+
+use num::complex::Complex;
+
+/// Shape whose closest approach by an orbit is recorded for orbit trap
+/// coloring, see [`crate::Escape::trap`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrbitTrap {
+    Point(Complex<f64>),
+    /// Line through `point`, `angle` degrees counterclockwise from the real
+    /// axis.
+    Line {
+        point: Complex<f64>,
+        angle: f64,
+    },
+    /// Horizontal and vertical line through a point.
+    Cross(Complex<f64>),
+    Circle {
+        center: Complex<f64>,
+        radius: f64,
+    },
+    /// Cross whose arms are `width` wide, after Clifford Pickover. Distances
+    /// are in units of `width`, and orbits that never come within it are not
+    /// trapped, so the stalks are drawn over the escape-time coloring.
+    Stalks {
+        center: Complex<f64>,
+        width: f64,
+    },
+}
+
+impl Default for OrbitTrap {
+    fn default() -> Self {
+        OrbitTrap::Point(Complex::new(0.0, 0.0))
+    }
+}
+
+/// Formats the trap the way `--trap` takes it, e.g. `circle:0,0,1`.
+impl fmt::Display for OrbitTrap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            OrbitTrap::Point(p) => write!(f, "point:{},{}", p.re, p.im),
+            OrbitTrap::Line { point, angle } => {
+                write!(f, "line:{},{},{angle}", point.re, point.im)
+            }
+            OrbitTrap::Cross(c) => write!(f, "cross:{},{}", c.re, c.im),
+            OrbitTrap::Circle { center, radius } => {
+                write!(f, "circle:{},{},{radius}", center.re, center.im)
+            }
+            OrbitTrap::Stalks { center, width } => {
+                write!(f, "stalks:{},{},{width}", center.re, center.im)
+            }
+        }
+    }
+}
+
+impl OrbitTrap {
+    /// Distance of `z` to the trap.
+    pub fn distance(&self, z: Complex<f64>) -> f64 {
+        match *self {
+            OrbitTrap::Point(point) => (z - point).norm(),
+            OrbitTrap::Line { point, angle } => {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                let d = z - point;
+                (d.im * cos - d.re * sin).abs()
+            }
+            OrbitTrap::Cross(center) => {
+                let d = z - center;
+                d.re.abs().min(d.im.abs())
+            }
+            OrbitTrap::Circle { center, radius } => ((z - center).norm() - radius).abs(),
+            OrbitTrap::Stalks { center, width } => {
+                let d = z - center;
+                d.re.abs().min(d.im.abs()) / width
+            }
+        }
+    }
+
+    /// Closest approach recorded for an orbit that came within `closest` of
+    /// the trap, or `None` if it was not trapped.
+    pub(crate) fn trapped(&self, closest: f64) -> Option<f64> {
+        match self {
+            OrbitTrap::Stalks { .. } if closest >= 1.0 => None,
+            _ => Some(closest),
+        }
+    }
+
+    /// The trap after this one when cycling through them in the viewer,
+    /// each centered on the origin.
+    pub fn next(&self) -> Self {
+        let origin = Complex::new(0.0, 0.0);
+        match self {
+            OrbitTrap::Point(_) => OrbitTrap::Line {
+                point: origin,
+                angle: 0.0,
+            },
+            OrbitTrap::Line { .. } => OrbitTrap::Cross(origin),
+            OrbitTrap::Cross(_) => OrbitTrap::Circle {
+                center: origin,
+                radius: 1.0,
+            },
+            OrbitTrap::Circle { .. } => OrbitTrap::Stalks {
+                center: origin,
+                width: 0.05,
+            },
+            OrbitTrap::Stalks { .. } => OrbitTrap::Point(origin),
+        }
+    }
+}
@@ -0,0 +1,481 @@
+use log::error;
+use mandelbrot::{Lighting, MandelbrotGrid, Supersampling};
+use pixels::{Error, Pixels, SurfaceTexture};
+use winit::{
+    dpi::{LogicalSize, PhysicalSize},
+    event::{Event, VirtualKeyCode},
+    event_loop::{ControlFlow, EventLoop},
+    window::WindowBuilder,
+};
+use winit_input_helper::WinitInputHelper;
+
+use crate::progressive::Renderer;
+
+/// Initial inner size of the window, in logical pixels.
+const WIDTH: u32 = 1000;
+const HEIGHT: u32 = 1000;
+/// Smallest inner size of the window, in logical pixels.
+const MIN_SIZE: u32 = 200;
+/// Fraction of the extent moved by one zoom or pan key press.
+const STEP: f64 = 0.2;
+/// Factor applied to the iteration limit by one key press.
+const ITERS_STEP: f64 = 1.25;
+/// The iteration limit is never lowered below this.
+const MIN_ITERS: usize = 16;
+/// Extent factor applied by one notch of the mouse wheel.
+const WHEEL_ZOOM: f64 = 0.8;
+/// Selections smaller than this many pixels on a side are ignored.
+const MIN_SELECTION: f64 = 4.0;
+/// Gradient lengths the palette is shifted by one key press.
+const PALETTE_STEP: f64 = 0.05;
+/// Degrees the light is rotated by one key press.
+const LIGHT_STEP: f64 = 15.0;
+/// Side length in pixels of the Julia preview inset.
+const INSET_SIZE: usize = 200;
+/// Distance in pixels between the inset and the frame edges.
+const INSET_MARGIN: usize = 10;
+/// Iteration limit of the inset, kept low so it follows the cursor smoothly.
+const INSET_MAX_ITERS: usize = 200;
+const LEFT_BUTTON: usize = 0;
+const RIGHT_BUTTON: usize = 1;
+
+/// Mouse drag in progress, in frame pixel coordinates.
+#[derive(Clone, Copy, Debug)]
+enum Drag {
+    /// Left button: moves the view with the cursor.
+    Pan {
+        start: (f64, f64),
+        current: (f64, f64),
+    },
+    /// Right button: rubber-band rectangle to zoom into.
+    Select {
+        start: (f64, f64),
+        current: (f64, f64),
+    },
+}
+
+/// Opens the interactive window and runs the event loop until it is closed.
+///
+/// The grid follows the physical size of the window, with one grid pixel
+/// per `render_scale` window pixels along each axis.
+pub fn run(render_scale: usize) -> Result<(), Error> {
+    let event_loop = EventLoop::new();
+    let mut input = WinitInputHelper::new();
+
+    let window = {
+        let size = LogicalSize::new(MIN_SIZE as f64, MIN_SIZE as f64);
+        let scaled_size = LogicalSize::new(WIDTH as f64, HEIGHT as f64);
+        WindowBuilder::new()
+            .with_title("mandelbrot rs")
+            .with_inner_size(scaled_size)
+            .with_min_inner_size(size)
+            .build(&event_loop)
+            .unwrap()
+    };
+
+    let window_size = window.inner_size();
+    let (width, height) = grid_size(window_size, render_scale);
+    let mut pixels = {
+        let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
+        Pixels::new(width as u32, height as u32, surface_texture)?
+    };
+
+    // Holds the view and settings; its cells are rendered by `renderer`.
+    let mut mandelbrot = MandelbrotGrid::new(width, height);
+    mandelbrot.viewport.fit(width, height);
+    let mut renderer = Renderer::new();
+    // Latest frame published by the renderer.
+    let mut image = vec![0; 4 * width * height];
+    update(&mut renderer, &mandelbrot);
+    let mut drag: Option<Drag> = None;
+    // Mandelbrot view to return to when leaving Julia mode.
+    let mut mandelbrot_viewport = mandelbrot.viewport.clone();
+
+    // Settings of the Julia set preview, rendered in the background like
+    // the main view so hovering never blocks the event loop.
+    let mut inset = MandelbrotGrid::new(INSET_SIZE, INSET_SIZE);
+    inset.max_iters = INSET_MAX_ITERS;
+    let mut inset_renderer = Renderer::new();
+    let mut inset_frame = vec![0; 4 * INSET_SIZE * INSET_SIZE];
+    let mut show_inset = false;
+
+    event_loop.run(move |event, _, control_flow| {
+        // The one and only event that winit_input_helper doesn't have for us...
+        if let Event::RedrawRequested(_) = event {
+            let (width, height) = (mandelbrot.width(), mandelbrot.height());
+            // Frames rendered before a resize are dropped.
+            if let Some(latest) = renderer.latest().filter(|f| f.pixels.len() == image.len()) {
+                image = latest.pixels;
+                if let Some(stats) = latest.stats {
+                    println!("{stats}");
+                }
+            }
+            let frame = pixels.frame_mut();
+            frame.copy_from_slice(&image);
+            match drag {
+                Some(Drag::Pan { start, current }) => shift_frame(
+                    frame,
+                    width,
+                    height,
+                    (current.0 - start.0) as isize,
+                    (current.1 - start.1) as isize,
+                ),
+                Some(Drag::Select { start, current }) => {
+                    let (end_x, end_y) = fit_aspect(start, current, width, height);
+                    draw_rect(frame, width, height, start, (end_x, end_y));
+                }
+                None => {}
+            }
+            if let Some(latest) = inset_renderer.latest() {
+                inset_frame = latest.pixels;
+            }
+            if show_inset && mandelbrot.julia.is_none() && inset.julia.is_some() {
+                draw_inset(frame, width, height, &inset_frame);
+            }
+            if let Err(err) = pixels.render() {
+                error!("pixels.render: {}", err);
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+        }
+
+        // For everything else, for let winit_input_helper collect events to build its state.
+        // It returns `true` when it is time to update our game state and request a redraw.
+        if input.update(&event) {
+            // Close events
+            if input.key_pressed(VirtualKeyCode::Escape) || input.close_requested() {
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+            let mut dirty = false;
+            // Resize the window, and the grid with it. Minimized windows
+            // report a size of zero.
+            if let Some(size) = input
+                .window_resized()
+                .filter(|s| s.width > 0 && s.height > 0)
+            {
+                if let Err(err) = pixels.resize_surface(size.width, size.height) {
+                    error!("pixels.resize_surface {}", err);
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                let from = (mandelbrot.width(), mandelbrot.height());
+                let to = grid_size(size, render_scale);
+                if to != from {
+                    if let Err(err) = pixels.resize_buffer(to.0 as u32, to.1 as u32) {
+                        error!("pixels.resize_buffer {}", err);
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    mandelbrot.viewport.resize(from, to);
+                    mandelbrot_viewport.resize(from, to);
+                    mandelbrot = mandelbrot.resized(to.0, to.1);
+                    image = vec![0; 4 * to.0 * to.1];
+                    dirty = true;
+                }
+            }
+            // Pan by whole pixels, so the cells still in view are reused.
+            let step_x = pan_step(mandelbrot.width());
+            let step_y = pan_step(mandelbrot.height());
+            let viewport = &mut mandelbrot.viewport;
+            if input.key_pressed_os(VirtualKeyCode::W) {
+                viewport.zoom(STEP);
+                dirty = true;
+            }
+            if input.key_pressed_os(VirtualKeyCode::S) {
+                viewport.zoom(-STEP);
+                dirty = true;
+            }
+            if input.key_pressed_os(VirtualKeyCode::Left) {
+                viewport.pan(-step_x, 0.0);
+                dirty = true;
+            }
+            if input.key_pressed_os(VirtualKeyCode::Right) {
+                viewport.pan(step_x, 0.0);
+                dirty = true;
+            }
+            if input.key_pressed_os(VirtualKeyCode::Up) {
+                viewport.pan(0.0, -step_y);
+                dirty = true;
+            }
+            if input.key_pressed_os(VirtualKeyCode::Down) {
+                viewport.pan(0.0, step_y);
+                dirty = true;
+            }
+            if input.key_pressed(VirtualKeyCode::C) {
+                mandelbrot.color_mode = mandelbrot.color_mode.next();
+                dirty = true;
+            }
+            if input.key_pressed(VirtualKeyCode::P) {
+                mandelbrot.palette = mandelbrot.palette.next();
+                println!("palette: {}", mandelbrot.palette.name);
+                dirty = true;
+            }
+            if input.key_pressed_os(VirtualKeyCode::LBracket) {
+                mandelbrot.palette.offset -= PALETTE_STEP;
+                dirty = true;
+            }
+            if input.key_pressed_os(VirtualKeyCode::RBracket) {
+                mandelbrot.palette.offset += PALETTE_STEP;
+                dirty = true;
+            }
+            if input.key_pressed(VirtualKeyCode::T) {
+                mandelbrot.trap = mandelbrot.trap.next();
+                dirty = true;
+            }
+            if input.key_pressed(VirtualKeyCode::N) {
+                mandelbrot.interior = mandelbrot.interior.next();
+                dirty = true;
+            }
+            if input.key_pressed(VirtualKeyCode::Q) {
+                mandelbrot.supersampling = Supersampling::next(mandelbrot.supersampling);
+                dirty = true;
+            }
+            if input.key_pressed(VirtualKeyCode::L) {
+                mandelbrot.lighting = Lighting::next(mandelbrot.lighting);
+                dirty = true;
+            }
+            if let Some(lighting) = &mut mandelbrot.lighting {
+                if input.key_pressed_os(VirtualKeyCode::Comma) {
+                    lighting.angle += LIGHT_STEP;
+                    dirty = true;
+                }
+                if input.key_pressed_os(VirtualKeyCode::Period) {
+                    lighting.angle -= LIGHT_STEP;
+                    dirty = true;
+                }
+            }
+            if input.key_pressed_os(VirtualKeyCode::Equals)
+                || input.key_pressed_os(VirtualKeyCode::NumpadAdd)
+            {
+                mandelbrot.max_iters = (mandelbrot.max_iters as f64 * ITERS_STEP).ceil() as usize;
+                dirty = true;
+            }
+            if input.key_pressed_os(VirtualKeyCode::Minus)
+                || input.key_pressed_os(VirtualKeyCode::NumpadSubtract)
+            {
+                mandelbrot.max_iters =
+                    ((mandelbrot.max_iters as f64 / ITERS_STEP) as usize).max(MIN_ITERS);
+                dirty = true;
+            }
+            if input.key_pressed(VirtualKeyCode::A) {
+                mandelbrot.auto_iters = !mandelbrot.auto_iters;
+                dirty = true;
+            }
+            // Mouse position in frame pixels, clamped to the frame.
+            let cursor = input.mouse().map(|pos| {
+                let (x, y) = pixels
+                    .window_pos_to_pixel(pos)
+                    .unwrap_or_else(|pos| pixels.clamp_pixel_pos(pos));
+                (x as f64, y as f64)
+            });
+            let (width, height) = (mandelbrot.width(), mandelbrot.height());
+            if let Some(cursor) = cursor {
+                let scroll = input.scroll_diff();
+                if scroll != 0.0 {
+                    let factor = WHEEL_ZOOM.powf(scroll as f64);
+                    mandelbrot
+                        .viewport
+                        .zoom_at_pixel(cursor.0, cursor.1, width, height, factor);
+                    dirty = true;
+                }
+                if drag.is_none() {
+                    if input.mouse_pressed(LEFT_BUTTON) {
+                        drag = Some(Drag::Pan {
+                            start: cursor,
+                            current: cursor,
+                        });
+                    } else if input.mouse_pressed(RIGHT_BUTTON) {
+                        drag = Some(Drag::Select {
+                            start: cursor,
+                            current: cursor,
+                        });
+                    }
+                }
+                match &mut drag {
+                    Some(Drag::Pan { current, .. } | Drag::Select { current, .. }) => {
+                        *current = cursor;
+                    }
+                    None => {}
+                }
+            }
+            match drag {
+                Some(Drag::Pan { start, current }) if !input.mouse_held(LEFT_BUTTON) => {
+                    let dx = (start.0 - current.0) / width as f64;
+                    let dy = (start.1 - current.1) / height as f64;
+                    if dx != 0.0 || dy != 0.0 {
+                        mandelbrot.viewport.pan(dx, dy);
+                        dirty = true;
+                    }
+                    drag = None;
+                }
+                Some(Drag::Select { start, current }) if !input.mouse_held(RIGHT_BUTTON) => {
+                    let end = fit_aspect(start, current, width, height);
+                    if (end.0 - start.0).abs() >= MIN_SELECTION {
+                        mandelbrot.viewport.select_pixels(start, end, width, height);
+                        dirty = true;
+                    }
+                    drag = None;
+                }
+                _ => {}
+            }
+            if input.key_pressed(VirtualKeyCode::F) {
+                mandelbrot.formula = mandelbrot.formula.next();
+                dirty = true;
+            }
+            if input.key_pressed(VirtualKeyCode::J) {
+                if mandelbrot.julia.take().is_some() {
+                    mandelbrot.viewport = mandelbrot_viewport.clone();
+                } else {
+                    let (re, im) = match cursor {
+                        Some((x, y)) => mandelbrot.viewport.subpixel_to_point(x, y, width, height),
+                        None => mandelbrot.viewport.center(),
+                    };
+                    mandelbrot_viewport = std::mem::take(&mut mandelbrot.viewport);
+                    mandelbrot.viewport.fit(width, height);
+                    mandelbrot.julia = Some((re, im));
+                }
+                dirty = true;
+            }
+            if input.key_pressed(VirtualKeyCode::I) {
+                show_inset = !show_inset;
+                inset.julia = None;
+            }
+            if input.key_pressed_os(VirtualKeyCode::Space) {
+                dirty = true;
+            }
+            if dirty {
+                update(&mut renderer, &mandelbrot);
+            }
+            // Preview the Julia set of the hovered point.
+            let exploring = show_inset && mandelbrot.julia.is_none() && drag.is_none();
+            if let (true, Some((x, y))) = (exploring, cursor) {
+                let point = mandelbrot.viewport.subpixel_to_point(x, y, width, height);
+                if inset.julia != Some(point) || dirty {
+                    inset.julia = Some(point);
+                    inset.color_mode = mandelbrot.color_mode;
+                    inset.palette = mandelbrot.palette.clone();
+                    inset.lighting = mandelbrot.lighting;
+                    inset.trap = mandelbrot.trap;
+                    inset.interior = mandelbrot.interior;
+                    inset.interior_palette = mandelbrot.interior_palette.clone();
+                    inset.formula = mandelbrot.formula;
+                    inset_renderer.render(&inset);
+                }
+            }
+            window.request_redraw();
+        }
+    });
+}
+
+/// Size of the grid rendered for a window of `size` physical pixels. The
+/// surface only scales the grid up by whole factors.
+fn grid_size(size: PhysicalSize<u32>, render_scale: usize) -> (usize, usize) {
+    let scaled = |pixels: u32| (pixels as usize / render_scale).max(1);
+    (scaled(size.width), scaled(size.height))
+}
+
+/// Starts rendering the current view in the background. Its timing is
+/// printed once the full resolution pass is shown.
+///
+/// The view is printed as `render` options, so it can be rendered again.
+fn update(renderer: &mut Renderer, mandelbrot: &MandelbrotGrid) {
+    renderer.render(mandelbrot);
+    let mut options = format!("--formula {}", mandelbrot.formula.name());
+    if let Some((re, im)) = mandelbrot.julia {
+        options += &format!(" --julia {re},{im}");
+    }
+    let viewport = &mandelbrot.viewport;
+    options += &format!(
+        " --center {},{} --extent {:e}",
+        viewport.center_re, viewport.center_im, viewport.extent_x
+    );
+    if mandelbrot.color_mode.uses_trap() {
+        options += &format!(" --coloring trap --trap {}", mandelbrot.trap);
+    }
+    println!("{options}");
+}
+
+/// Moves the corner `end` of a selection starting at `start` so the selection
+/// has the frame's aspect ratio and the zoomed view is not distorted.
+fn fit_aspect(start: (f64, f64), end: (f64, f64), width: usize, height: usize) -> (f64, f64) {
+    let aspect = width as f64 / height as f64;
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let (w, h) = if dx.abs() > dy.abs() * aspect {
+        (dx.abs(), dx.abs() / aspect)
+    } else {
+        (dy.abs() * aspect, dy.abs())
+    };
+    (start.0 + w.copysign(dx), start.1 + h.copysign(dy))
+}
+
+/// [`STEP`] of a grid `pixels` wide, rounded to whole pixels, as a fraction
+/// of its extent.
+fn pan_step(pixels: usize) -> f64 {
+    (STEP * pixels as f64).round() / pixels as f64
+}
+
+/// Shifts the frame contents by `(dx, dy)` pixels, filling the exposed area
+/// with black. Used to preview a pan while the mouse is dragged.
+fn shift_frame(frame: &mut [u8], width: usize, height: usize, dx: isize, dy: isize) {
+    let shifted = frame.to_vec();
+    for (y, row) in frame.chunks_exact_mut(4 * width).enumerate() {
+        for (x, pix) in row.chunks_exact_mut(4).enumerate() {
+            let src_x = x as isize - dx;
+            let src_y = y as isize - dy;
+            if (0..width as isize).contains(&src_x) && (0..height as isize).contains(&src_y) {
+                let src = 4 * (src_x as usize + src_y as usize * width);
+                pix.copy_from_slice(&shifted[src..src + 4]);
+            } else {
+                pix.copy_from_slice(&[0, 0, 0, 255]);
+            }
+        }
+    }
+}
+
+/// Draws the outline of the rectangle spanned by `a` and `b` by inverting the
+/// pixels underneath, so it stays visible on any palette.
+fn draw_rect(frame: &mut [u8], width: usize, height: usize, a: (f64, f64), b: (f64, f64)) {
+    let clamp_x = |v: f64| (v.max(0.0) as usize).min(width - 1);
+    let clamp_y = |v: f64| (v.max(0.0) as usize).min(height - 1);
+    let (x0, x1) = (clamp_x(a.0.min(b.0)), clamp_x(a.0.max(b.0)));
+    let (y0, y1) = (clamp_y(a.1.min(b.1)), clamp_y(a.1.max(b.1)));
+    let mut invert = |x: usize, y: usize| {
+        let idx = 4 * (x + y * width);
+        for c in &mut frame[idx..idx + 3] {
+            *c = 255 - *c;
+        }
+    };
+    for x in x0..=x1 {
+        invert(x, y0);
+        if y1 != y0 {
+            invert(x, y1);
+        }
+    }
+    for y in y0 + 1..y1 {
+        invert(x0, y);
+        if x1 != x0 {
+            invert(x1, y);
+        }
+    }
+}
+
+/// Copies the inset image into the top right corner of the frame with a
+/// one pixel white border.
+fn draw_inset(frame: &mut [u8], width: usize, height: usize, inset: &[u8]) {
+    if width < INSET_SIZE + INSET_MARGIN + 1 || height < INSET_SIZE + INSET_MARGIN + 1 {
+        return;
+    }
+    let left = width - INSET_SIZE - INSET_MARGIN;
+    let top = INSET_MARGIN;
+    for y in top - 1..=top + INSET_SIZE {
+        for x in left - 1..=left + INSET_SIZE {
+            let idx = 4 * (x + y * width);
+            frame[idx..idx + 4].copy_from_slice(&[255, 255, 255, 255]);
+        }
+    }
+    for (row, src) in inset.chunks_exact(4 * INSET_SIZE).enumerate() {
+        let idx = 4 * (left + (top + row) * width);
+        frame[idx..idx + src.len()].copy_from_slice(src);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::bignum::{BigComplex, BigFixed, DEFAULT_FRAC_BITS};
+
+/// Pixel spacing, in f64 ulps of the coordinates, below which rendering
+/// switches to high precision.
+const HIGH_PRECISION_MARGIN: f64 = 256.0;
+/// Fraction bits kept beyond the extent, enough to address pixels of grids
+/// up to 2^16 wide with 32 bits to spare.
+const GUARD_BITS: u32 = 48;
+
+/// Rectangle of the complex plane that is mapped onto the pixel grid.
+///
+/// The center is stored in arbitrary precision so the view can be zoomed far
+/// beyond the resolution of `f64`; the extents are relative and stay `f64`.
+/// The top row of pixels has the smallest imaginary part, so the imaginary
+/// axis points down the screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Viewport {
+    pub center_re: BigFixed,
+    pub center_im: BigFixed,
+    /// Extent along the real axis.
+    pub extent_x: f64,
+    /// Extent along the imaginary axis.
+    pub extent_y: f64,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::centered(0.0, 0.0, 5.0)
+    }
+}
+
+impl Viewport {
+    pub fn new(min_x: f64, max_x: f64, min_y: f64, max_y: f64) -> Self {
+        Self::with_center(
+            BigFixed::from_f64((min_x + max_x) / 2.0, DEFAULT_FRAC_BITS),
+            BigFixed::from_f64((min_y + max_y) / 2.0, DEFAULT_FRAC_BITS),
+            max_x - min_x,
+            max_y - min_y,
+        )
+    }
+
+    /// Builds a viewport centered on `(cx, cy)` spanning `extent` along both axes.
+    pub fn centered(cx: f64, cy: f64, extent: f64) -> Self {
+        let half = extent / 2.0;
+        Self::new(cx - half, cx + half, cy - half, cy + half)
+    }
+
+    /// Builds a viewport around a high precision center.
+    pub fn with_center(
+        center_re: BigFixed,
+        center_im: BigFixed,
+        extent_x: f64,
+        extent_y: f64,
+    ) -> Self {
+        Self {
+            center_re,
+            center_im,
+            extent_x,
+            extent_y,
+        }
+    }
+
+    /// Builds a viewport around a high precision center for a
+    /// `width`x`height` grid of square pixels `scale` wide.
+    pub fn with_scale(
+        center_re: BigFixed,
+        center_im: BigFixed,
+        scale: f64,
+        width: usize,
+        height: usize,
+    ) -> Self {
+        Self::with_center(
+            center_re,
+            center_im,
+            scale * width as f64,
+            scale * height as f64,
+        )
+    }
+
+    /// Center rounded to `f64`.
+    pub fn center(&self) -> (f64, f64) {
+        (self.center_re.to_f64(), self.center_im.to_f64())
+    }
+
+    pub fn width(&self) -> f64 {
+        self.extent_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.extent_y
+    }
+
+    /// Magnification relative to the default viewport.
+    pub fn zoom_factor(&self) -> f64 {
+        Self::default().width() / self.width()
+    }
+
+    /// Fraction bits needed for positions inside the viewport.
+    pub fn precision_bits(&self) -> u32 {
+        let extent = self.extent_x.abs().min(self.extent_y.abs());
+        let bits = (-extent.log2()).ceil().max(0.0) as u32 + GUARD_BITS;
+        bits.max(DEFAULT_FRAC_BITS)
+    }
+
+    /// Whether a `width`x`height` grid has pixels too close together to
+    /// be told apart in `f64`.
+    pub fn needs_high_precision(&self, width: usize, height: usize) -> bool {
+        let spacing = (self.extent_x / width as f64)
+            .abs()
+            .min((self.extent_y / height as f64).abs());
+        let (cx, cy) = self.center();
+        let magnitude = cx.abs().max(cy.abs()).max(1.0);
+        spacing < magnitude * f64::EPSILON * HIGH_PRECISION_MARGIN
+    }
+
+    /// Widens the view along one axis so a `width`x`height` grid has square
+    /// pixels, keeping all of the current view visible.
+    pub fn fit(&mut self, width: usize, height: usize) {
+        let scale = (self.extent_x / width as f64).max(self.extent_y / height as f64);
+        self.set_scale(scale, width, height);
+    }
+
+    /// Follows a grid resized from `from` to `to` pixels, keeping the center
+    /// and the size of a pixel, so more or less of the plane comes into view.
+    pub fn resize(&mut self, from: (usize, usize), to: (usize, usize)) {
+        let scale = self.extent_x / from.0 as f64;
+        self.set_scale(scale, to.0, to.1);
+    }
+
+    fn set_scale(&mut self, scale: f64, width: usize, height: usize) {
+        self.extent_x = scale * width as f64;
+        self.extent_y = scale * height as f64;
+    }
+
+    /// Shrinks (positive `fraction`) or grows (negative) every side by
+    /// `fraction` of the current extent.
+    pub fn zoom(&mut self, fraction: f64) {
+        self.extent_x *= 1.0 - 2.0 * fraction;
+        self.extent_y *= 1.0 - 2.0 * fraction;
+    }
+
+    /// Moves the viewport by the given fractions of its extent.
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.move_center(self.extent_x * dx, self.extent_y * dy);
+    }
+
+    /// Scales the extent by `factor` around the fractional pixel `(x, y)` of a
+    /// `width`x`height` grid, which keeps its position on screen.
+    pub fn zoom_at_pixel(&mut self, x: f64, y: f64, width: usize, height: usize, factor: f64) {
+        let (dx, dy) = self.pixel_offset(x, y, width, height);
+        self.move_center(dx * (1.0 - factor), dy * (1.0 - factor));
+        self.extent_x *= factor;
+        self.extent_y *= factor;
+    }
+
+    /// Zooms onto the rectangle spanned by the fractional pixels `a` and `b`
+    /// of a `width`x`height` grid, widened to the grid's aspect ratio.
+    pub fn select_pixels(&mut self, a: (f64, f64), b: (f64, f64), width: usize, height: usize) {
+        let (ax, ay) = self.pixel_offset(a.0, a.1, width, height);
+        let (bx, by) = self.pixel_offset(b.0, b.1, width, height);
+        self.move_center((ax + bx) / 2.0, (ay + by) / 2.0);
+        self.extent_x = (bx - ax).abs();
+        self.extent_y = (by - ay).abs();
+        self.fit(width, height);
+    }
+
+    fn move_center(&mut self, dx: f64, dy: f64) {
+        let frac_bits = self.precision_bits();
+        self.center_re = &self.center_re + &BigFixed::from_f64(dx, frac_bits);
+        self.center_im = &self.center_im + &BigFixed::from_f64(dy, frac_bits);
+    }
+
+    /// Maps the center of pixel `(x, y)` of a `width`x`height` grid onto the
+    /// complex plane.
+    pub fn pixel_to_point(&self, x: usize, y: usize, width: usize, height: usize) -> (f64, f64) {
+        self.subpixel_to_point(x as f64 + 0.5, y as f64 + 0.5, width, height)
+    }
+
+    /// Maps fractional pixel coordinates onto the complex plane, where pixel
+    /// `(x, y)` covers `x..x + 1` and `y..y + 1`.
+    pub fn subpixel_to_point(&self, x: f64, y: f64, width: usize, height: usize) -> (f64, f64) {
+        let (cx, cy) = self.center();
+        let (dx, dy) = self.pixel_offset(x, y, width, height);
+        (cx + dx, cy + dy)
+    }
+
+    /// Offset of the fractional pixel `(x, y)` from the center, which unlike
+    /// the absolute position stays accurate in `f64` at any depth.
+    pub fn pixel_offset(&self, x: f64, y: f64, width: usize, height: usize) -> (f64, f64) {
+        let dx = (x / width as f64 - 0.5) * self.extent_x;
+        let dy = (y / height as f64 - 0.5) * self.extent_y;
+        (dx, dy)
+    }
+
+    /// Exact position of the fractional pixel `(x, y)` with `frac_bits`
+    /// fraction bits.
+    pub fn subpixel_to_big(
+        &self,
+        x: f64,
+        y: f64,
+        width: usize,
+        height: usize,
+        frac_bits: u32,
+    ) -> BigComplex {
+        let (dx, dy) = self.pixel_offset(x, y, width, height);
+        BigComplex::new(
+            &self.center_re + &BigFixed::from_f64(dx, frac_bits),
+            &self.center_im + &BigFixed::from_f64(dy, frac_bits),
+        )
+    }
+}
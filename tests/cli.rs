@@ -0,0 +1,88 @@
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use mandelbrot::{render, Viewport, MAX_ITERS};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mandelbrot-rs"))
+        .args(args)
+        .output()
+        .expect("failed to run mandelbrot-rs")
+}
+
+/// Path in the temporary directory unique to this test process.
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("mandelbrot-cli-{}-{name}", std::process::id()))
+}
+
+#[test]
+fn render_writes_the_library_image_as_png() {
+    let path = temp_path("render.png");
+    let output = run(&[
+        "render",
+        "--center",
+        "-0.5,0",
+        "--extent",
+        "3",
+        "--width",
+        "48",
+        "--height",
+        "32",
+        "-o",
+        path.to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let decoder = png::Decoder::new(File::open(&path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!((info.width, info.height), (48, 32));
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+
+    // `--extent` is the width, the height follows from square pixels.
+    let viewport = Viewport::new(-2.0, 1.0, -1.0, 1.0);
+    let mut expected = vec![0; 4 * 48 * 32];
+    render(viewport, 48, 32, MAX_ITERS, &mut expected);
+    assert!(pixels == expected, "PNG differs from the library render");
+}
+
+#[test]
+fn bad_arguments_exit_with_usage() {
+    for args in [
+        &["render", "--width", "abc", "-o", "unused.png"][..],
+        &["render", "--width", "8"],
+        &["render", "--center", "1", "-o", "unused.png"],
+        &["frobnicate"],
+    ] {
+        let output = run(args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(2), "{args:?}: {stderr}");
+        assert!(stderr.starts_with("error: "), "{args:?}: {stderr}");
+        assert!(stderr.contains("usage:"), "{args:?}: {stderr}");
+    }
+}
+
+#[test]
+fn io_errors_exit_with_failure() {
+    let path = temp_path("missing").join("out.png");
+    let output = run(&[
+        "render",
+        "--width",
+        "8",
+        "--height",
+        "8",
+        "-o",
+        path.to_str().unwrap(),
+    ]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{stderr}");
+    assert!(stderr.contains("out.png"), "{stderr}");
+    assert!(!stderr.contains("usage:"), "{stderr}");
+}
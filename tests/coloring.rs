@@ -0,0 +1,102 @@
+use std::collections::{HashMap, HashSet};
+
+use mandelbrot::{escape, ColorMode, MandelbrotGrid, Palette, Repeat, Viewport, MAX_ITERS};
+
+const SIZE: usize = 96;
+
+#[test]
+fn histogram_is_monotonic_in_escape_count() {
+    let mut grid = MandelbrotGrid::new(SIZE, SIZE);
+    grid.viewport = Viewport::centered(-0.75, 0.1, 0.2);
+    grid.color_mode = ColorMode::Histogram;
+    // Brightens steadily over the whole range the distribution spans.
+    grid.palette = Palette::builtin("grayscale").unwrap();
+    grid.palette.repeat = Repeat::Clamp;
+    grid.update();
+
+    let max_iters = grid.max_iters;
+    let mut exterior: Vec<_> = grid
+        .cells()
+        .iter()
+        .zip(grid.rgba().chunks_exact(4))
+        .filter(|(cell, _)| cell.steps < max_iters)
+        .map(|(cell, rgba)| (cell.smooth, rgba[0]))
+        .collect();
+    assert!(exterior.len() > SIZE, "too few exterior pixels");
+    exterior.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for pair in exterior.windows(2) {
+        assert!(pair[0].1 <= pair[1].1, "{pair:?}");
+    }
+    // Equalized, the colors span the palette.
+    let (darkest, brightest) = (exterior[0].1, exterior[exterior.len() - 1].1);
+    assert!(darkest < 32 && brightest > 223, "{darkest}..{brightest}");
+}
+
+#[test]
+fn smooth_count_is_continuous_across_bands() {
+    // A finely sampled line just above the set, crossing several bands.
+    let escapes: Vec<_> = (0..10_000)
+        .map(|i| escape(-1.0 + 1.5 * i as f64 / 10_000.0, 1.125, MAX_ITERS))
+        .collect();
+    for e in &escapes {
+        assert!(e.steps < MAX_ITERS, "{e:?}");
+        assert!((e.smooth - e.steps as f64).abs() < 2.0, "{e:?}");
+    }
+    let mut band_edges = 0;
+    for pair in escapes.windows(2) {
+        if pair[0].steps != pair[1].steps {
+            band_edges += 1;
+            let jump = (pair[0].smooth - pair[1].smooth).abs();
+            assert!(jump < 0.1, "smooth count jumps by {jump} at a band edge");
+        }
+    }
+    assert!(band_edges > 5, "too few bands");
+}
+
+#[test]
+fn banded_coloring_is_kept() {
+    let mut grid = MandelbrotGrid::new(SIZE, SIZE);
+    grid.viewport = Viewport::centered(-0.75, 0.1, 0.2);
+    let pixel_colors = |grid: &MandelbrotGrid| {
+        let mut colors = HashMap::new();
+        for (cell, rgba) in grid.cells().iter().zip(grid.rgba().chunks_exact(4)) {
+            colors
+                .entry(cell.steps)
+                .or_insert_with(HashSet::new)
+                .insert(rgba.to_vec());
+        }
+        colors
+    };
+    grid.color_mode = ColorMode::Banded;
+    grid.update();
+    // One color per escape count.
+    assert!(pixel_colors(&grid).values().all(|colors| colors.len() == 1));
+    grid.color_mode = ColorMode::Smooth;
+    grid.update();
+    assert!(pixel_colors(&grid).values().any(|colors| colors.len() > 1));
+}
+
+#[test]
+fn recolor_matches_a_fresh_render() {
+    let mut grid = MandelbrotGrid::new(SIZE, SIZE);
+    grid.viewport = Viewport::centered(-0.75, 0.1, 0.2);
+    grid.update();
+    let cells = grid.cells().to_vec();
+    for (name, mode) in [
+        ("fire", ColorMode::Smooth),
+        ("viridis", ColorMode::Histogram),
+    ] {
+        grid.color_mode = mode;
+        grid.recolor(&Palette::builtin(name).unwrap());
+        let unchanged = (grid.cells().iter().zip(&cells))
+            .all(|(a, b)| a.steps == b.steps && a.smooth == b.smooth);
+        assert!(unchanged, "{name}: cells changed");
+
+        let mut fresh = grid.resized(SIZE, SIZE);
+        let stats = fresh.update();
+        assert_eq!(stats.reused_pixels, 0);
+        assert!(grid.rgba() == fresh.rgba(), "{name}: colors differ");
+        // Nothing is left to iterate for this view.
+        assert_eq!(grid.update().reused_pixels, SIZE * SIZE, "{name}");
+    }
+}
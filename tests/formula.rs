@@ -0,0 +1,58 @@
+use mandelbrot::{BurningShip, Celtic, Complex, Formula, Fractal, Mandelbrot, Multibrot, Tricorn};
+
+#[test]
+fn multibrot_bailout_covers_low_powers() {
+    // Orbits of z^1.5 + c only surely diverge beyond |z| = 2^(1 / 0.5).
+    let formula = Multibrot { power: 1.5 };
+    assert!(!formula.escaped(Complex::new(3.9, 0.0)));
+    assert!(formula.escaped(Complex::new(0.0, -4.1)));
+    // From power 2 on the usual radius of 2 holds.
+    for power in [2.0, 3.0, 7.5] {
+        let formula = Multibrot { power };
+        assert!(!formula.escaped(Complex::new(1.9, 0.0)), "{power}");
+        assert!(formula.escaped(Complex::new(-1.5, 1.5)), "{power}");
+    }
+}
+
+/// Asserts that `fractal` behaves like `formula` at a few points.
+fn assert_forwards(fractal: Fractal, formula: impl Formula) {
+    let name = fractal.name();
+    let c = Complex::new(-0.4, 0.2);
+    for z in [
+        Complex::new(0.3, -0.7),
+        Complex::new(1.5, 1.5),
+        Complex::new(-0.1, 0.0),
+    ] {
+        assert_eq!(fractal.initial_z(z), formula.initial_z(z), "{name}");
+        assert_eq!(fractal.step(z, c), formula.step(z, c), "{name}");
+        assert_eq!(
+            fractal.step_delta(z, c, c),
+            formula.step_delta(z, c, c),
+            "{name}"
+        );
+        assert_eq!(fractal.derivative(z), formula.derivative(z), "{name}");
+        assert_eq!(
+            fractal.second_derivative(z),
+            formula.second_derivative(z),
+            "{name}"
+        );
+        assert_eq!(fractal.escaped(z), formula.escaped(z), "{name}");
+        assert_eq!(
+            fractal.known_interior(z),
+            formula.known_interior(z),
+            "{name}"
+        );
+    }
+    assert_eq!(fractal.degree(), formula.degree(), "{name}");
+    assert_eq!(fractal.quadratic(), formula.quadratic(), "{name}");
+}
+
+#[test]
+fn fractal_forwards_to_its_formula() {
+    assert_forwards(Fractal::Mandelbrot, Mandelbrot);
+    assert_forwards(Fractal::BurningShip, BurningShip);
+    assert_forwards(Fractal::Tricorn, Tricorn);
+    assert_forwards(Fractal::Multibrot(1.5), Multibrot { power: 1.5 });
+    assert_forwards(Fractal::Multibrot(4.0), Multibrot { power: 4.0 });
+    assert_forwards(Fractal::Celtic, Celtic);
+}
@@ -0,0 +1,52 @@
+use mandelbrot::{MandelbrotGrid, Viewport, MAX_ITERS};
+
+const SIZE: usize = 32;
+
+#[test]
+fn grid_iterates_up_to_its_own_limit() {
+    let mut grid = MandelbrotGrid::new(SIZE, SIZE);
+    grid.viewport = Viewport::centered(-0.5, 0.0, 3.0);
+    grid.max_iters = 40;
+    let stats = grid.update();
+    assert_eq!(stats.max_iters, 40);
+    let cells = grid.cells();
+    assert!(cells.iter().all(|cell| cell.steps <= 40));
+    // The center of the main cardioid runs up to the limit.
+    assert_eq!(cells[SIZE / 2 + SIZE / 2 * SIZE].steps, 40);
+
+    grid.max_iters = 2 * MAX_ITERS;
+    assert_eq!(grid.update().max_iters, 2 * MAX_ITERS);
+    assert_eq!(
+        grid.cells()[SIZE / 2 + SIZE / 2 * SIZE].steps,
+        2 * MAX_ITERS
+    );
+}
+
+#[test]
+fn auto_limit_grows_with_the_zoom_depth() {
+    let mut grid = MandelbrotGrid::new(SIZE, SIZE);
+    assert_eq!(grid.iteration_limit(), MAX_ITERS);
+    grid.viewport.zoom(0.25);
+    assert_eq!(grid.iteration_limit(), MAX_ITERS, "auto mode is off");
+
+    grid.auto_iters = true;
+    grid.viewport = Viewport::default();
+    assert_eq!(grid.iteration_limit(), MAX_ITERS);
+    // Zooming out never lowers the limit below `max_iters`.
+    grid.viewport = Viewport::centered(0.0, 0.0, 20.0);
+    assert_eq!(grid.iteration_limit(), MAX_ITERS);
+
+    // Every doubling of the zoom factor adds the same number of iterations.
+    let limits: Vec<_> = (1..=4)
+        .map(|doublings| {
+            grid.viewport = Viewport::centered(0.0, 0.0, 5.0 / f64::powi(2.0, doublings));
+            grid.iteration_limit()
+        })
+        .collect();
+    let per_octave = limits[0] - MAX_ITERS;
+    assert!(per_octave > 0);
+    for (doublings, limit) in (1..).zip(&limits) {
+        assert_eq!(*limit, MAX_ITERS + doublings * per_octave);
+    }
+    assert_eq!(grid.update().max_iters, limits[3]);
+}
@@ -0,0 +1,50 @@
+use mandelbrot::{escape_julia, MandelbrotGrid, Viewport};
+
+const SIZE: usize = 48;
+
+fn julia_grid(c: (f64, f64)) -> MandelbrotGrid {
+    let mut grid = MandelbrotGrid::new(SIZE, SIZE);
+    grid.viewport = Viewport::centered(0.0, 0.0, 3.0);
+    grid.julia = Some(c);
+    grid.update();
+    grid
+}
+
+#[test]
+fn pixels_are_the_starting_points() {
+    let c = (-0.12, 0.75);
+    let grid = julia_grid(c);
+    for (i, cell) in grid.cells().iter().enumerate() {
+        let (x, y) = grid.viewport.pixel_to_point(i % SIZE, i / SIZE, SIZE, SIZE);
+        let expected = escape_julia(x, y, c, grid.max_iters);
+        assert_eq!(cell.steps, expected.steps, "{x},{y}");
+        assert_eq!(cell.smooth, expected.smooth, "{x},{y}");
+    }
+}
+
+#[test]
+fn julia_set_of_zero_is_the_unit_disc() {
+    let grid = julia_grid((0.0, 0.0));
+    for (i, cell) in grid.cells().iter().enumerate() {
+        let (x, y) = grid.viewport.pixel_to_point(i % SIZE, i / SIZE, SIZE, SIZE);
+        let radius = x.hypot(y);
+        let interior = cell.steps == grid.max_iters;
+        if (radius - 1.0).abs() > 0.05 {
+            assert_eq!(interior, radius < 1.0, "{x},{y}");
+        }
+    }
+}
+
+#[test]
+fn switching_to_the_julia_set_recomputes() {
+    let mut grid = MandelbrotGrid::new(SIZE, SIZE);
+    grid.update();
+    let mandelbrot = grid.cells().to_vec();
+    grid.julia = Some((-0.12, 0.75));
+    let stats = grid.update();
+    assert_eq!(stats.reused_pixels, 0);
+    let differing = (grid.cells().iter().zip(&mandelbrot))
+        .filter(|(a, b)| a.steps != b.steps)
+        .count();
+    assert!(differing > SIZE * SIZE / 4, "{differing} cells changed");
+}
@@ -0,0 +1,212 @@
+use mandelbrot::{
+    escape_distance, escape_lanes, escape_parameter, escape_statistics, escape_with, Average,
+    Complex, Derivative, Mandelbrot, Shortcut, Statistics, LANES,
+};
+
+const MAX_ITERS: usize = 1000;
+
+/// Iterates `points` four at a time and one at a time, asserting the same
+/// results.
+fn assert_lanes_match_scalar(
+    z: impl Fn(Complex<f64>) -> Complex<f64>,
+    c: impl Fn(Complex<f64>) -> Complex<f64>,
+    points: &[Complex<f64>],
+) {
+    for chunk in points.chunks_exact(LANES) {
+        let zs = std::array::from_fn(|lane| z(chunk[lane]));
+        let cs = std::array::from_fn(|lane| c(chunk[lane]));
+        let lanes = escape_lanes(zs, cs, MAX_ITERS);
+        for lane in 0..LANES {
+            let scalar = escape_with(&Mandelbrot, zs[lane], cs[lane], MAX_ITERS);
+            let point = chunk[lane];
+            assert_eq!(lanes[lane].steps, scalar.steps, "{point}");
+            assert_eq!(lanes[lane].smooth, scalar.smooth, "{point}");
+            assert_eq!(lanes[lane].shortcut, scalar.shortcut, "{point}");
+        }
+    }
+}
+
+/// A 64x48 grid over the rectangle from `min` to `max`.
+fn grid(min: (f64, f64), max: (f64, f64)) -> Vec<Complex<f64>> {
+    let (width, height) = (64, 48);
+    (0..width * height)
+        .map(|i| {
+            let (x, y) = ((i % width) as f64, (i / width) as f64);
+            Complex::new(
+                min.0 + (max.0 - min.0) * x / width as f64,
+                min.1 + (max.1 - min.1) * y / height as f64,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn lanes_match_scalar_on_the_set() {
+    let zero = Complex::new(0.0, 0.0);
+    assert_lanes_match_scalar(|_| zero, |c| c, &grid((-2.5, -1.3), (1.0, 1.3)));
+}
+
+#[test]
+fn lanes_match_scalar_inside_the_set() {
+    // Mostly the main cardioid and the period 2 bulb, which stop at a period.
+    let zero = Complex::new(0.0, 0.0);
+    assert_lanes_match_scalar(|_| zero, |c| c, &grid((-1.2, -0.3), (0.2, 0.3)));
+}
+
+#[test]
+fn lanes_match_scalar_on_a_julia_set() {
+    // The Douady rabbit, whose interior orbits settle into a 3-cycle.
+    let c = Complex::new(-0.12, 0.75);
+    assert_lanes_match_scalar(|z| z, |_| c, &grid((-1.5, -1.2), (1.5, 1.2)));
+}
+
+#[test]
+fn known_interior_parameters_skip_iterating() {
+    // The main cardioid and the period 2 bulb.
+    for (re, im) in [
+        (0.0, 0.0),
+        (-0.1, 0.1),
+        (0.25, 0.0),
+        (-1.0, 0.0),
+        (-1.1, 0.1),
+    ] {
+        let escape = escape_parameter(&Mandelbrot, Complex::new(re, im), MAX_ITERS);
+        assert_eq!(escape.steps, MAX_ITERS, "{re},{im}");
+        assert_eq!(escape.shortcut, Some(Shortcut::KnownInterior), "{re},{im}");
+    }
+}
+
+#[test]
+fn periodic_orbits_stop_early() {
+    // Centers of the period 3 and 4 components off the cardioid and bulb.
+    for (c, period) in [
+        (Complex::new(-0.122561, 0.744862), 3),
+        (Complex::new(-1.310703, 0.0), 4),
+    ] {
+        let escape = escape_parameter(&Mandelbrot, c, MAX_ITERS);
+        assert_eq!(escape.steps, MAX_ITERS, "{c}");
+        match escape.shortcut {
+            Some(Shortcut::Period(p)) => assert_eq!(p % period, 0, "{c}: period {p}"),
+            other => panic!("{c}: {other:?}"),
+        }
+    }
+    // Interior points of a Julia set as well.
+    let rabbit = Complex::new(-0.12, 0.75);
+    let escape = escape_with(&Mandelbrot, Complex::new(0.0, 0.0), rabbit, MAX_ITERS);
+    assert_eq!(escape.steps, MAX_ITERS);
+    assert!(matches!(escape.shortcut, Some(Shortcut::Period(_))));
+}
+
+#[test]
+fn exterior_points_take_no_shortcut() {
+    for (re, im) in [(0.5, 0.0), (-2.1, 0.0), (0.3, 0.6), (-0.75, 0.1)] {
+        let escape = escape_parameter(&Mandelbrot, Complex::new(re, im), MAX_ITERS);
+        assert!(escape.steps < MAX_ITERS, "{re},{im}");
+        assert_eq!(escape.shortcut, None, "{re},{im}");
+    }
+}
+
+#[test]
+fn distance_estimate_grows_away_from_the_boundary() {
+    let distance = |c: Complex<f64>| {
+        escape_distance(
+            &Mandelbrot,
+            Complex::new(0.0, 0.0),
+            c,
+            MAX_ITERS,
+            Derivative::Parameter,
+        )
+        .distance
+        .unwrap_or_else(|| panic!("{c}: no estimate"))
+    };
+    // Outwards from the cusp of the cardioid at 1/4, the tip at -2 and the
+    // Misiurewicz point i.
+    for (boundary, outwards) in [
+        (Complex::new(0.25, 0.0), Complex::new(1.0, 0.0)),
+        (Complex::new(-2.0, 0.0), Complex::new(-1.0, 0.0)),
+        (Complex::new(0.0, 1.0), Complex::new(0.0, 1.0)),
+    ] {
+        let estimates: Vec<f64> = [1e-4, 1e-2, 0.1, 1.0]
+            .iter()
+            .map(|&d| distance(boundary + outwards * d))
+            .collect();
+        assert!(estimates[0] < 1e-3, "{boundary}: {estimates:?}");
+        assert!(
+            estimates.windows(2).all(|w| w[0] < w[1]),
+            "{boundary}: {estimates:?}"
+        );
+    }
+    // Within the factor of four of the true distance, 3/4 to the cusp.
+    let far = distance(Complex::new(1.0, 0.0));
+    assert!((0.75 / 4.0..=0.75 * 4.0).contains(&far), "{far}");
+}
+
+#[test]
+fn orbit_averages_stay_in_range() {
+    let points = grid((-2.5, -1.3), (1.0, 1.3));
+    for average in [
+        Average::Stripe {
+            density: 5.0,
+            skip: 1,
+        },
+        Average::Curvature { skip: 1 },
+        Average::TriangleInequality { skip: 1 },
+    ] {
+        let statistics = Statistics {
+            average: Some(average),
+            ..Statistics::default()
+        };
+        let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+        for &c in &points {
+            let escape = escape_statistics(
+                &Mandelbrot,
+                Complex::new(0.0, 0.0),
+                c,
+                MAX_ITERS,
+                statistics,
+            );
+            if escape.steps == MAX_ITERS {
+                assert_eq!(escape.average, None, "{average:?} at {c}");
+                continue;
+            }
+            let value = escape
+                .average
+                .unwrap_or_else(|| panic!("{average:?} at {c}"));
+            assert!((0.0..=1.0).contains(&value), "{average:?} at {c}: {value}");
+            (min, max) = (min.min(value), max.max(value));
+        }
+        // Not stuck at one end of the range.
+        assert!(max - min > 0.5, "{average:?}: {min}..{max}");
+    }
+}
+
+#[test]
+fn interior_cycles() {
+    let statistics = Statistics {
+        cycle: Some(Derivative::Parameter),
+        ..Statistics::default()
+    };
+    let cycle = |re, im| {
+        let c = Complex::new(re, im);
+        let escape = escape_statistics(
+            &Mandelbrot,
+            Complex::new(0.0, 0.0),
+            c,
+            MAX_ITERS,
+            statistics,
+        );
+        assert_eq!(escape.steps, MAX_ITERS, "{c}");
+        escape.cycle.unwrap_or_else(|| panic!("{c}: no cycle"))
+    };
+    // The nucleus of the period 2 bulb and a point off it.
+    for (re, im) in [(-1.0, 0.0), (-1.1, 0.1)] {
+        let cycle = cycle(re, im);
+        assert_eq!(cycle.period, 2, "{re},{im}");
+        let multiplier = cycle.multiplier.unwrap().norm();
+        assert!(multiplier < 1.0, "{re},{im}: {multiplier}");
+        assert!(cycle.distance.unwrap() > 0.0, "{re},{im}");
+    }
+    assert!(cycle(-1.0, 0.0).multiplier.unwrap().norm() < 1e-9);
+    // The main cardioid has period 1.
+    assert_eq!(cycle(-0.1, 0.1).period, 1);
+}
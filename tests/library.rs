@@ -0,0 +1,33 @@
+use mandelbrot::{render, MandelbrotGrid, Viewport, MAX_ITERS};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 48;
+
+#[test]
+fn render_fills_the_buffer_like_a_grid() {
+    let mut viewport = Viewport::default();
+    viewport.fit(WIDTH, HEIGHT);
+    let mut buffer = vec![0; 4 * WIDTH * HEIGHT];
+    let stats = render(viewport.clone(), WIDTH, HEIGHT, MAX_ITERS, &mut buffer);
+    assert_eq!(stats.max_iters, MAX_ITERS);
+
+    let mut grid = MandelbrotGrid::new(WIDTH, HEIGHT);
+    grid.viewport = viewport;
+    grid.update();
+    let mut expected = vec![0; 4 * WIDTH * HEIGHT];
+    grid.draw(&mut expected);
+    assert!(buffer == expected, "render differs from the grid");
+
+    // The center lies in the set, the corners far outside of it.
+    let pixel = |x: usize, y: usize| &buffer[4 * (x + y * WIDTH)..][..4];
+    assert_eq!(pixel(WIDTH / 2, HEIGHT / 2), [0, 0, 0, 255]);
+    assert_ne!(pixel(0, 0), [0, 0, 0, 255]);
+    assert!(buffer.chunks_exact(4).all(|pix| pix[3] == 255));
+}
+
+#[test]
+#[should_panic(expected = "buffer size mismatch")]
+fn render_rejects_a_wrong_buffer_size() {
+    let mut buffer = vec![0; 4 * WIDTH * WIDTH];
+    render(Viewport::default(), WIDTH, HEIGHT, MAX_ITERS, &mut buffer);
+}
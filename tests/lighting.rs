@@ -0,0 +1,97 @@
+use mandelbrot::{Complex, Lighting, MandelbrotGrid, Shading, Viewport};
+
+const GRAY: [u8; 4] = [128, 128, 128, 200];
+
+fn brightness(rgba: [u8; 4]) -> u32 {
+    rgba[..3].iter().map(|&c| c as u32).sum()
+}
+
+/// Unit normal pointing `degrees` counterclockwise from the real axis.
+fn normal(degrees: f64) -> Complex<f64> {
+    Complex::from_polar(1.0, degrees.to_radians())
+}
+
+#[test]
+fn slopes_facing_the_light_are_brighter() {
+    for shading in [Shading::Lambert, Shading::BlinnPhong] {
+        for angle in [0.0, 45.0, 200.0] {
+            let lighting = Lighting {
+                shading,
+                angle,
+                ..Lighting::default()
+            };
+            // Rows go down the imaginary axis, so the image mirrors the
+            // angles of the plane.
+            let towards = lighting.shade(GRAY, normal(-angle));
+            let away = lighting.shade(GRAY, normal(180.0 - angle));
+            assert!(
+                brightness(towards) > brightness(away),
+                "{shading:?} at {angle}: {towards:?} {away:?}"
+            );
+            assert_eq!(towards[3], GRAY[3]);
+        }
+    }
+}
+
+#[test]
+fn blinn_phong_adds_a_highlight() {
+    let lambert = Lighting {
+        shading: Shading::Lambert,
+        ..Lighting::default()
+    };
+    let blinn_phong = Lighting {
+        shading: Shading::BlinnPhong,
+        ..lambert
+    };
+    let facing = normal(-lambert.angle);
+    assert!(brightness(blinn_phong.shade(GRAY, facing)) > brightness(lambert.shade(GRAY, facing)));
+}
+
+#[test]
+fn intensity_blends_with_the_palette_color() {
+    let off = Lighting {
+        intensity: 0.0,
+        ..Lighting::default()
+    };
+    let half = Lighting {
+        intensity: 0.5,
+        ..off
+    };
+    let full = Lighting {
+        intensity: 1.0,
+        ..off
+    };
+    let away = normal(180.0 - off.angle);
+    assert_eq!(off.shade(GRAY, away), GRAY);
+    let (half, full) = (half.shade(GRAY, away), full.shade(GRAY, away));
+    assert!(brightness(full) < brightness(half) && brightness(half) < brightness(GRAY));
+}
+
+#[test]
+fn grid_shades_the_exterior_only() {
+    let mut grid = MandelbrotGrid::new(64, 64);
+    grid.viewport = Viewport::centered(-0.745, 0.11, 0.02);
+    grid.update();
+    let unlit = grid.rgba().to_vec();
+
+    grid.lighting = Some(Lighting::default());
+    grid.update();
+    let (mut interior, mut shaded) = (0, 0);
+    for (lit, unlit) in grid.rgba().chunks_exact(4).zip(unlit.chunks_exact(4)) {
+        if unlit == [0, 0, 0, 255] {
+            assert_eq!(lit, unlit, "the black interior was shaded");
+            interior += 1;
+        } else if lit != unlit {
+            shaded += 1;
+        }
+    }
+    assert!(interior > 0, "no interior in view");
+    assert!(shaded > 64 * 64 / 2, "only {shaded} pixels were shaded");
+
+    grid.lighting = Some(Lighting {
+        intensity: 0.0,
+        ..Lighting::default()
+    });
+    grid.update();
+    assert!(grid.rgba() == unlit, "intensity 0 changed the colors");
+}
@@ -0,0 +1,158 @@
+use mandelbrot::{
+    hsl_to_rgba, Interpolation, MandelbrotGrid, Palette, PaletteError, Repeat, Stop, Viewport,
+};
+
+#[test]
+fn builtins_load() {
+    for name in Palette::builtin_names() {
+        let palette = Palette::builtin(name).expect(name);
+        assert_eq!(palette.name, name);
+        assert!(palette.stops().len() >= 2, "{name}");
+    }
+    assert_eq!(Palette::default().name, "ultra");
+}
+
+#[test]
+fn interpolation_modes() {
+    let (black, white) = ([0, 0, 0], [255, 255, 255]);
+    assert_eq!(Interpolation::Rgb.mix(black, white, 0.5), [128, 128, 128]);
+    // Half the light is well above half the sRGB value.
+    assert_eq!(
+        Interpolation::LinearRgb.mix(black, white, 0.5),
+        [188, 188, 188]
+    );
+    let gray = Interpolation::Oklab.mix(black, white, 0.5);
+    assert!(gray[0] == gray[1] && gray[1] == gray[2] && (90..=110).contains(&gray[0]));
+    for mode in [
+        Interpolation::Rgb,
+        Interpolation::LinearRgb,
+        Interpolation::Oklab,
+    ] {
+        assert_eq!(mode.mix([12, 200, 99], white, 0.0), [12, 200, 99]);
+        assert_eq!(mode.mix([12, 200, 99], white, 1.0), white);
+    }
+}
+
+#[test]
+fn repeat_modes() {
+    let stops = vec![Stop::new(0.0, [0, 0, 0]), Stop::new(1.0, [200, 0, 0])];
+    let mut palette = Palette::new("red", stops).unwrap();
+    palette.interpolation = Interpolation::Rgb;
+    palette.repeat = Repeat::Wrap;
+    assert_eq!(palette.at(1.25), [50, 0, 0]);
+    palette.repeat = Repeat::Mirror;
+    assert_eq!(palette.at(1.25), [150, 0, 0]);
+    palette.repeat = Repeat::Clamp;
+    assert_eq!(palette.at(1.25), [200, 0, 0]);
+    assert_eq!(palette.at(-3.0), [0, 0, 0]);
+}
+
+#[test]
+fn fractint_map() {
+    let palette = Palette::from_map("test", "0 0 0\n255 0 0 red\n\n0 0 255 blue\n").unwrap();
+    let positions: Vec<_> = palette.stops().iter().map(|s| s.position).collect();
+    assert_eq!(positions, [0.0, 0.5, 1.0]);
+    assert_eq!(palette.stops()[2].color, [0, 0, 255]);
+    assert!(matches!(
+        Palette::from_map("bad", "0 0 0\n0 300 0\n"),
+        Err(PaletteError::Parse { line: 2, .. })
+    ));
+}
+
+#[test]
+fn gimp_gradient() {
+    let ggr = "GIMP Gradient\nName: Two\n2\n\
+               0.0 0.25 0.5 0 0 0 1 1 0 0 1 0 0\n\
+               0.5 0.75 1.0 1 0 0 1 1 1 1 1 0 0\n";
+    let palette = Palette::from_ggr(ggr).unwrap();
+    assert_eq!(palette.name, "Two");
+    assert_eq!(palette.interpolation, Interpolation::Rgb);
+    assert_eq!(palette.stops().len(), 6);
+    assert_eq!(palette.at(0.25), [128, 0, 0]);
+    assert_eq!(palette.at(0.875), [255, 192, 192]);
+}
+
+#[test]
+fn json_and_toml_agree() {
+    let json = r##"{
+        "interpolation": "linear-rgb",
+        "repeat": "clamp",
+        "offset": 0.5,
+        "stops": [
+            { "position": 1.0, "color": "#FFFFFF" },
+            { "position": 0.0, "color": "#102030" }
+        ]
+    }"##;
+    let toml = r##"
+        # Same palette as the JSON one.
+        interpolation = "linear-rgb"
+        repeat = "clamp"
+        offset = 0.5
+
+        [[stops]]
+        position = 1.0
+        color = "#FFFFFF"
+
+        [[stops]]
+        position = 0.0 # first
+        color = "#102030"
+    "##;
+    let from_json = Palette::from_json("test", json).unwrap();
+    let from_toml = Palette::from_toml("test", toml).unwrap();
+    assert_eq!(from_json, from_toml);
+    assert_eq!(from_json.stops()[0].color, [0x10, 0x20, 0x30]);
+    assert_eq!(from_json.interpolation, Interpolation::LinearRgb);
+    assert_eq!(from_json.repeat, Repeat::Clamp);
+    assert_eq!(from_json.offset, 0.5);
+}
+
+#[test]
+fn stop_list_errors() {
+    let err = Palette::from_json(
+        "test",
+        "{\n  \"stops\": [\n    { \"position\": 2.0, \"color\": \"#000000\" }\n  ]\n}",
+    )
+    .unwrap_err();
+    assert!(matches!(err, PaletteError::Parse { .. }), "{err}");
+    let err =
+        Palette::from_json("test", "{ \"stops\": [ { \"color\": \"#00000\" } ] }").unwrap_err();
+    assert!(err.to_string().contains("invalid color"), "{err}");
+    let err = Palette::from_toml("test", "[[stops]]\nposition = 0\ncolor = #000000\n").unwrap_err();
+    assert!(matches!(err, PaletteError::Parse { line: 3, .. }), "{err}");
+}
+
+#[test]
+fn classic_matches_original_mapping() {
+    let palette = Palette::builtin("classic").unwrap();
+    let max_iters = 1000;
+    for steps in [0, 1, 7, 50, 333, 999, max_iters] {
+        let norm = steps as f64 / max_iters as f64;
+        let hue = f64::powf(norm * 360.0, 1.5) % 360.0;
+        assert_eq!(
+            palette.color(norm),
+            hsl_to_rgba(hue, 50.0, norm * 100.0),
+            "{steps}"
+        );
+    }
+    // Like the original, the set is white.
+    assert_eq!(palette.color(1.0), [255, 255, 255, 255]);
+    // The stops follow the same hue circle at half lightness.
+    for hue in [0.0, 45.0, 120.0, 200.0, 300.0] {
+        let [r, g, b] = palette.at(hue / 360.0);
+        let [hr, hg, hb, _] = hsl_to_rgba(hue, 50.0, 50.0);
+        assert!(
+            r.abs_diff(hr) <= 1 && g.abs_diff(hg) <= 1 && b.abs_diff(hb) <= 1,
+            "{hue}"
+        );
+    }
+}
+
+#[test]
+fn classic_interior_is_white() {
+    // A view inside the main cardioid.
+    let mut grid = MandelbrotGrid::new(16, 16);
+    grid.viewport = Viewport::centered(-0.25, 0.0, 0.1);
+    grid.palette = Palette::builtin("classic").unwrap();
+    grid.update();
+    assert!(grid.rgba().iter().all(|&channel| channel == 255));
+}
@@ -0,0 +1,80 @@
+use mandelbrot::{
+    BigComplex, BigFixed, Complex, Formula, Mandelbrot, MandelbrotGrid, Precision, UpdateStats,
+    Viewport, MAX_ITERS,
+};
+
+const SIZE: usize = 64;
+/// Smaller, as the boundary takes thousands of iterations per pixel.
+const BOUNDARY_SIZE: usize = 24;
+
+/// The Mandelbrot formula without `step_delta`, which renders deep views in
+/// arbitrary precision instead of perturbation.
+struct Arbitrary;
+
+impl Formula for Arbitrary {
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        Mandelbrot.step(z, c)
+    }
+
+    fn step_big(&self, z: &BigComplex, c: &BigComplex) -> Option<BigComplex> {
+        Mandelbrot.step_big(z, c)
+    }
+}
+
+/// Renders `viewport` on a `size`x`size` grid with perturbation and in
+/// arbitrary precision.
+fn render_both(
+    viewport: Viewport,
+    size: usize,
+    max_iters: usize,
+) -> (MandelbrotGrid, MandelbrotGrid, UpdateStats) {
+    let mut perturbed = MandelbrotGrid::new(size, size);
+    perturbed.viewport = viewport;
+    perturbed.max_iters = max_iters;
+    let stats = perturbed.update();
+    assert_eq!(stats.precision, Precision::Perturbation);
+
+    let mut arbitrary = perturbed.resized(size, size);
+    let arbitrary_stats = arbitrary.update_with(&Arbitrary);
+    assert_eq!(arbitrary_stats.precision, Precision::Arbitrary);
+    (perturbed, arbitrary, stats)
+}
+
+fn assert_matches_arbitrary(re: f64, im: f64, extent: f64) {
+    let center = (BigFixed::from_f64(re, 128), BigFixed::from_f64(im, 128));
+    let viewport = Viewport::with_center(center.0, center.1, extent, extent);
+    let (perturbed, arbitrary, _) = render_both(viewport, SIZE, MAX_ITERS);
+    for (a, b) in perturbed.cells().iter().zip(arbitrary.cells()) {
+        assert_eq!(a.steps, b.steps, "{re},{im}");
+        assert!((a.smooth - b.smooth).abs() < 1e-6, "{re},{im}");
+    }
+}
+
+#[test]
+fn deep_zoom_outside_the_set() {
+    // References escaping at once, after one step and after many.
+    assert_matches_arbitrary(3.0, 0.0, 1e-20);
+    assert_matches_arbitrary(2.5, 0.0, 1e-20);
+    assert_matches_arbitrary(0.3, 0.0, 1e-20);
+}
+
+#[test]
+fn deep_zoom_on_the_boundary() {
+    // The deep zoom of the README, whose orbits come close enough to 0 to
+    // be rebased.
+    let re = "-0.743643887037158704752191506114774".parse().unwrap();
+    let im = "0.131825904205311970493132056385139".parse().unwrap();
+    let viewport = Viewport::with_center(re, im, 1e-17, 1e-17);
+    let (perturbed, arbitrary, stats) = render_both(viewport, BOUNDARY_SIZE, 10_000);
+    assert!(stats.rebased_pixels > 0, "nothing was rebased");
+    // After some 8000 iterations close to the boundary, a few pixels of
+    // either rendering have lost their accuracy.
+    let cells = perturbed.cells().iter().zip(arbitrary.cells());
+    let differing = cells
+        .filter(|(a, b)| (a.smooth - b.smooth).abs() > 1.0)
+        .count();
+    assert!(
+        differing <= BOUNDARY_SIZE * BOUNDARY_SIZE / 100,
+        "{differing} pixels differ"
+    );
+}
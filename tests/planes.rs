@@ -0,0 +1,38 @@
+use std::mem::size_of;
+
+use mandelbrot::{escape, Cell, MandelbrotGrid, Viewport};
+
+const WIDTH: usize = 40;
+const HEIGHT: usize = 30;
+
+#[test]
+fn cells_are_compact() {
+    // Only the escape and smooth counts; colors and orbit statistics live
+    // in planes of their own.
+    assert!(size_of::<Cell>() <= 16, "{} bytes", size_of::<Cell>());
+}
+
+#[test]
+fn planes_hold_one_entry_per_pixel_in_row_order() {
+    let mut grid = MandelbrotGrid::new(WIDTH, HEIGHT);
+    grid.viewport = Viewport::centered(-0.5, 0.0, 3.0);
+    grid.viewport.fit(WIDTH, HEIGHT);
+    grid.update();
+    assert_eq!(grid.cells().len(), WIDTH * HEIGHT);
+    assert_eq!(grid.rgba().len(), 4 * WIDTH * HEIGHT);
+    for (i, cell) in grid.cells().iter().enumerate() {
+        let (x, y) = grid
+            .viewport
+            .pixel_to_point(i % WIDTH, i / WIDTH, WIDTH, HEIGHT);
+        let expected = escape(x, y, grid.max_iters);
+        assert_eq!(cell.steps, expected.steps, "{x},{y}");
+        assert_eq!(cell.smooth, expected.smooth, "{x},{y}");
+    }
+    let mut screen = vec![0; 4 * WIDTH * HEIGHT];
+    grid.draw(&mut screen);
+    assert!(screen == grid.rgba(), "draw differs from the RGBA plane");
+
+    let resized = grid.resized(2 * WIDTH, HEIGHT);
+    assert_eq!(resized.cells().len(), 2 * WIDTH * HEIGHT);
+    assert_eq!(resized.rgba().len(), 8 * WIDTH * HEIGHT);
+}
@@ -0,0 +1,95 @@
+use std::sync::atomic::AtomicBool;
+
+use mandelbrot::{
+    BurningShip, ColorMode, InteriorMode, Lighting, MandelbrotGrid, Supersampling, Viewport,
+};
+
+const SIZE: usize = 128;
+
+/// A view whose pixel positions are exact in `f64`, so cells reused after a
+/// whole pixel pan are identical to freshly computed ones.
+fn grid() -> MandelbrotGrid {
+    let mut grid = MandelbrotGrid::new(SIZE, SIZE);
+    grid.viewport = Viewport::centered(-0.5, 0.0, 4.0);
+    grid
+}
+
+fn pan(grid: &mut MandelbrotGrid, x: isize, y: isize) {
+    grid.viewport
+        .pan(x as f64 / SIZE as f64, y as f64 / SIZE as f64);
+}
+
+fn assert_matches_fresh(name: &str, grid: &MandelbrotGrid) {
+    let mut fresh = grid.resized(SIZE, SIZE);
+    fresh.update();
+    let differing = grid
+        .cells()
+        .iter()
+        .zip(fresh.cells())
+        .filter(|(a, b)| a.steps != b.steps || a.smooth != b.smooth)
+        .count();
+    assert_eq!(differing, 0, "{name}: {differing} cells differ");
+    assert!(grid.rgba() == fresh.rgba(), "{name}: colors differ");
+}
+
+#[test]
+fn pan_matches_fresh_render() {
+    let mut grid = grid();
+    grid.update();
+    for (x, y) in [(8, 0), (-3, 5), (0, -17)] {
+        pan(&mut grid, x, y);
+        let stats = grid.update();
+        assert!(stats.reused_pixels > 0, "pan by {x},{y} reused nothing");
+        assert_matches_fresh(&format!("pan by {x},{y}"), &grid);
+    }
+}
+
+#[test]
+fn other_formula_is_not_reused() {
+    let mut grid = grid();
+    grid.update_with(&BurningShip);
+    pan(&mut grid, 8, 0);
+    let stats = grid.update();
+    assert_eq!(stats.reused_pixels, 0);
+    assert_matches_fresh("after update_with", &grid);
+}
+
+#[test]
+fn cancelled_update_leaves_grid_untouched() {
+    let mut grid = grid();
+    grid.supersampling = Some(Supersampling::default());
+    grid.update();
+    let smooth_counts =
+        |grid: &MandelbrotGrid| grid.cells().iter().map(|cell| cell.smooth).collect();
+    let (smooth, rgba): (Vec<f64>, _) = (smooth_counts(&grid), grid.rgba().to_vec());
+    pan(&mut grid, 8, 0);
+    assert!(grid.update_cancellable(&AtomicBool::new(true)).is_none());
+    assert!(smooth_counts(&grid) == smooth, "cells changed");
+    assert!(grid.rgba() == rgba, "colors changed");
+    // Nothing was marked as computed for the new view either.
+    let stats = grid.update();
+    assert!(stats.reused_pixels > 0);
+    assert_matches_fresh("after cancelling", &grid);
+}
+
+#[test]
+fn switching_coloring_keeps_the_statistics_it_needs() {
+    let mut grid = grid();
+    grid.interior = InteriorMode::Period;
+    grid.update();
+    let pan_and_check = |grid: &mut MandelbrotGrid, name| {
+        pan(grid, 3, 0);
+        grid.update();
+        assert_matches_fresh(name, grid);
+    };
+    grid.interior = InteriorMode::Magnitude;
+    pan_and_check(&mut grid, "magnitude interior");
+    grid.lighting = Some(Lighting::default());
+    pan_and_check(&mut grid, "lighting");
+    grid.color_mode = ColorMode::Distance {
+        line_width: ColorMode::DEFAULT_LINE_WIDTH,
+    };
+    pan_and_check(&mut grid, "distance coloring");
+    grid.color_mode = ColorMode::Trap;
+    pan_and_check(&mut grid, "trap coloring");
+}
@@ -0,0 +1,159 @@
+use mandelbrot::{ColorMode, Fractal, Lighting, MandelbrotGrid, Strategy, Viewport};
+
+/// Resolutions the views are compared at, as the pixels sampling the borders
+/// of the rectangles shift with the size of the grid.
+const SIZES: &[(usize, usize)] = &[(160, 120), (240, 180), (800, 600)];
+
+/// Renders `setup` with `strategy`, returning the image and the number of
+/// filled pixels.
+fn render(
+    setup: impl Fn(&mut MandelbrotGrid),
+    strategy: Strategy,
+    (width, height): (usize, usize),
+) -> (Vec<u8>, usize) {
+    let mut grid = MandelbrotGrid::new(width, height);
+    grid.color_mode = ColorMode::Banded;
+    setup(&mut grid);
+    grid.strategy = strategy;
+    let stats = grid.update();
+    let mut buffer = vec![0; 4 * width * height];
+    grid.draw(&mut buffer);
+    (buffer, stats.filled_pixels)
+}
+
+/// Asserts that subdivision renders `setup` like the full strategy at every
+/// size, with banded coloring unless `setup` picks another. Returns the
+/// number of filled pixels over all sizes.
+fn assert_matches_full(
+    name: &str,
+    sizes: &[(usize, usize)],
+    setup: impl Fn(&mut MandelbrotGrid),
+) -> usize {
+    let mut filled = 0;
+    for &size in sizes {
+        let (full, _) = render(&setup, Strategy::Full, size);
+        let (subdivided, filled_pixels) = render(&setup, Strategy::Subdivide, size);
+        let differing = full
+            .chunks_exact(4)
+            .zip(subdivided.chunks_exact(4))
+            .filter(|(a, b)| a != b)
+            .count();
+        assert_eq!(
+            differing, 0,
+            "{name} at {size:?}: {differing} pixels differ"
+        );
+        filled += filled_pixels;
+    }
+    filled
+}
+
+#[test]
+fn default_view() {
+    let filled = assert_matches_full("default", SIZES, |grid| {
+        grid.viewport = Viewport::centered(-0.5, 0.0, 3.0);
+    });
+    assert!(filled > 0, "nothing was filled");
+}
+
+#[test]
+fn zoomed_out() {
+    // The whole set fits inside rectangles bordered by uniform exterior.
+    for extent in [40.0, 1000.0] {
+        let filled = assert_matches_full("zoomed out", SIZES, |grid| {
+            grid.viewport = Viewport::centered(0.0, 0.0, extent);
+        });
+        assert!(filled > 0, "nothing was filled at extent {extent}");
+    }
+}
+
+#[test]
+fn seahorse_valley() {
+    let filled = assert_matches_full("seahorse", &[(240, 180), (640, 480)], |grid| {
+        grid.viewport = Viewport::centered(-0.75, 0.1, 0.1);
+        grid.max_iters = 1000;
+    });
+    assert!(filled > 0, "nothing was filled");
+}
+
+#[test]
+fn period_3_bulb() {
+    let filled = assert_matches_full("period 3", SIZES, |grid| {
+        grid.viewport = Viewport::centered(-0.1528, 1.0397, 0.05);
+    });
+    assert!(filled > 0, "nothing was filled");
+}
+
+#[test]
+fn other_formulas() {
+    for formula in [
+        Fractal::BurningShip,
+        Fractal::Tricorn,
+        Fractal::Multibrot(3.0),
+    ] {
+        let filled = assert_matches_full(&formula.name(), SIZES, |grid| {
+            grid.viewport = Viewport::centered(-0.25, 0.0, 2.0);
+            grid.formula = formula;
+        });
+        assert!(filled > 0, "{}: nothing was filled", formula.name());
+    }
+}
+
+#[test]
+fn connected_julia_set() {
+    let filled = assert_matches_full("julia", SIZES, |grid| {
+        grid.viewport = Viewport::centered(0.0, 0.0, 200.0);
+        grid.julia = Some((-0.12, 0.75));
+    });
+    assert!(filled > 0, "nothing was filled");
+}
+
+#[test]
+fn disconnected_julia_set() {
+    // Uniform rings may enclose some of the islands of dust.
+    let filled = assert_matches_full("dust", SIZES, |grid| {
+        grid.viewport = Viewport::centered(0.0, 0.0, 3.0);
+        grid.julia = Some((0.5, 0.5));
+    });
+    assert_eq!(filled, 0);
+}
+
+#[test]
+fn colorings_reading_more_than_escape_counts() {
+    let view = |grid: &mut MandelbrotGrid| grid.viewport = Viewport::centered(-0.5, 0.0, 3.0);
+    let smooth = assert_matches_full("smooth", SIZES, |grid| {
+        view(grid);
+        grid.color_mode = ColorMode::Smooth;
+    });
+    let distance = assert_matches_full("distance", SIZES, |grid| {
+        view(grid);
+        grid.color_mode = ColorMode::Distance {
+            line_width: ColorMode::DEFAULT_LINE_WIDTH,
+        };
+    });
+    let lighting = assert_matches_full("lighting", SIZES, |grid| {
+        view(grid);
+        grid.lighting = Some(Lighting::default());
+    });
+    assert_eq!((smooth, distance, lighting), (0, 0, 0));
+}
+
+#[test]
+fn filled_cells_are_recomputed_for_other_colorings() {
+    let size = SIZES[0];
+    let setup = |grid: &mut MandelbrotGrid| grid.viewport = Viewport::centered(-0.5, 0.0, 3.0);
+    let mut grid = MandelbrotGrid::new(size.0, size.1);
+    grid.color_mode = ColorMode::Banded;
+    setup(&mut grid);
+    assert!(grid.update().filled_pixels > 0);
+    grid.color_mode = ColorMode::Smooth;
+    assert_eq!(grid.update().reused_pixels, 0);
+    let (full, _) = render(
+        |grid| {
+            setup(grid);
+            grid.color_mode = ColorMode::Smooth;
+        },
+        Strategy::Full,
+        size,
+    );
+    assert!(grid.rgba() == full, "filled cells were recolored");
+}
@@ -0,0 +1,33 @@
+use mandelbrot::{Complex, OrbitTrap};
+
+#[test]
+fn formats_as_cli_syntax() {
+    let at = Complex::new(-0.5, 0.25);
+    for (trap, text) in [
+        (OrbitTrap::Point(at), "point:-0.5,0.25"),
+        (
+            OrbitTrap::Line {
+                point: at,
+                angle: 45.0,
+            },
+            "line:-0.5,0.25,45",
+        ),
+        (OrbitTrap::Cross(at), "cross:-0.5,0.25"),
+        (
+            OrbitTrap::Circle {
+                center: at,
+                radius: 1.5,
+            },
+            "circle:-0.5,0.25,1.5",
+        ),
+        (
+            OrbitTrap::Stalks {
+                center: at,
+                width: 0.01,
+            },
+            "stalks:-0.5,0.25,0.01",
+        ),
+    ] {
+        assert_eq!(trap.to_string(), text);
+    }
+}
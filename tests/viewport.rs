@@ -0,0 +1,97 @@
+use mandelbrot::Viewport;
+
+const WIDTH: usize = 400;
+const HEIGHT: usize = 300;
+
+fn assert_close(a: (f64, f64), b: (f64, f64)) {
+    let error = (a.0 - b.0).abs().max((a.1 - b.1).abs());
+    assert!(error < 1e-12, "{a:?} != {b:?}");
+}
+
+fn viewport() -> Viewport {
+    let mut viewport = Viewport::centered(-0.5, 0.25, 3.0);
+    viewport.fit(WIDTH, HEIGHT);
+    viewport
+}
+
+#[test]
+fn wheel_zoom_keeps_the_point_under_the_cursor() {
+    let mut viewport = viewport();
+    let cursor = (310.5, 42.25);
+    let point = viewport.subpixel_to_point(cursor.0, cursor.1, WIDTH, HEIGHT);
+    for factor in [0.8, 1.25, 0.1] {
+        let width = viewport.width();
+        viewport.zoom_at_pixel(cursor.0, cursor.1, WIDTH, HEIGHT, factor);
+        assert!((viewport.width() - width * factor).abs() < 1e-12);
+        assert_close(
+            viewport.subpixel_to_point(cursor.0, cursor.1, WIDTH, HEIGHT),
+            point,
+        );
+    }
+}
+
+#[test]
+fn drag_pans_by_fractions_of_the_extent() {
+    let mut viewport = viewport();
+    let (cx, cy) = viewport.center();
+    // Dragging by 40 pixels to the right moves the view 40 pixels left.
+    viewport.pan(-40.0 / WIDTH as f64, 30.0 / HEIGHT as f64);
+    let pixel = viewport.width() / WIDTH as f64;
+    assert_close(viewport.center(), (cx - 40.0 * pixel, cy + 30.0 * pixel));
+    // Fitted to 4:3, the height of 3 stays and the width grows to 4.
+    assert_eq!(viewport.width(), 4.0);
+}
+
+#[test]
+fn selection_zooms_onto_the_rectangle() {
+    let mut viewport = viewport();
+    // Dragged from the bottom right to the top left, taller than the grid.
+    let (a, b) = ((220.0, 250.0), (180.0, 50.0));
+    let corners = (
+        viewport.subpixel_to_point(b.0, b.1, WIDTH, HEIGHT),
+        viewport.subpixel_to_point(a.0, a.1, WIDTH, HEIGHT),
+    );
+    viewport.select_pixels(a, b, WIDTH, HEIGHT);
+
+    let middle = (
+        (corners.0 .0 + corners.1 .0) / 2.0,
+        (corners.0 .1 + corners.1 .1) / 2.0,
+    );
+    assert_close(viewport.center(), middle);
+    // The selection spans the full height, with square pixels.
+    assert!((viewport.height() - (corners.1 .1 - corners.0 .1)).abs() < 1e-12);
+    let ratio = viewport.width() / viewport.height();
+    assert!((ratio - WIDTH as f64 / HEIGHT as f64).abs() < 1e-12);
+}
+
+#[test]
+fn fit_keeps_the_view_with_square_pixels() {
+    for (width, height) in [(640, 480), (300, 900), (100, 100)] {
+        let mut viewport = Viewport::default();
+        viewport.fit(width, height);
+        let (dx, dy) = (
+            viewport.width() / width as f64,
+            viewport.height() / height as f64,
+        );
+        assert!((dx - dy).abs() < 1e-15, "{width}x{height}: {dx} != {dy}");
+        // The whole default view stays visible.
+        assert!(viewport.width() >= 5.0 && viewport.height() >= 5.0);
+        assert!(viewport.width() == 5.0 || viewport.height() == 5.0);
+        assert_eq!(viewport.center(), (0.0, 0.0));
+    }
+}
+
+#[test]
+fn resize_shows_more_of_the_plane_at_the_same_scale() {
+    let mut viewport = viewport();
+    let center = viewport.center();
+    let pixel = viewport.width() / WIDTH as f64;
+    viewport.resize((WIDTH, HEIGHT), (1000, 250));
+    assert_eq!(viewport.center(), center);
+    assert!((viewport.width() - 1000.0 * pixel).abs() < 1e-12);
+    assert!((viewport.height() - 250.0 * pixel).abs() < 1e-12);
+    // Resizing back restores the view.
+    viewport.resize((1000, 250), (WIDTH, HEIGHT));
+    assert!((viewport.width() - 4.0).abs() < 1e-12);
+    assert!((viewport.height() - 3.0).abs() < 1e-12);
+}